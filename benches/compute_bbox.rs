@@ -0,0 +1,93 @@
+#[macro_use]
+extern crate criterion;
+extern crate geojson;
+extern crate par_bbox;
+
+use criterion::Criterion;
+use geojson::{Feature, FeatureCollection, Geometry, Position, Value};
+use par_bbox::{BboxOptions, GrainSize, SplitBy, Strategy, ToBbox};
+
+
+// A closed ring of `n` vertices wiggling around a circle, like a coastline.
+fn ring(n: usize, cx: f64, cy: f64) -> Vec<Position> {
+    let mut positions: Vec<Position> = (0..n)
+        .map(|i| {
+            let t = i as f64 / n as f64 * 2.0 * std::f64::consts::PI;
+            let r = 1.0 + 0.1 * (t * 50.0).sin();
+            vec![cx + r * t.cos(), cy + r * t.sin()]
+        })
+        .collect();
+    let first = positions[0].clone();
+    positions.push(first);
+    positions
+}
+
+
+fn bench_value(c: &mut Criterion, name: &str, value: &Value) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(20);
+    let grains = [("single elements", GrainSize::Fixed(1)), ("adaptive", GrainSize::Adaptive)];
+    for &(label, grain_size) in &grains {
+        let options = BboxOptions::default().with_grain_size(grain_size);
+        group.bench_function(label, |b| b.iter(|| value.to_bbox_with(&options).unwrap()));
+    }
+    group.finish();
+}
+
+
+fn line_string(c: &mut Criterion) {
+    let value = Value::LineString(ring(1_000_000, 0.0, 0.0));
+    bench_value(c, "LineString 1M vertices", &value);
+}
+
+
+fn multi_polygon(c: &mut Criterion) {
+    let polygons = (0..100)
+        .map(|i| vec![ring(10_000, i as f64 * 3.0, 0.0)])
+        .collect();
+    let value = Value::MultiPolygon(polygons);
+    bench_value(c, "MultiPolygon 100 x 10k vertices", &value);
+}
+
+
+fn strategies(c: &mut Criterion) {
+    let value = Value::LineString(ring(1_000_000, 0.0, 0.0));
+    let mut group = c.benchmark_group("Strategies on LineString 1M vertices");
+    group.sample_size(20);
+    for &(label, strategy) in &[("join", Strategy::Join), ("reduce", Strategy::Reduce),
+                                ("sequential", Strategy::Sequential)] {
+        let options = BboxOptions::default().with_strategy(strategy);
+        group.bench_function(label, |b| b.iter(|| value.to_bbox_with(&options).unwrap()));
+    }
+    group.finish();
+}
+
+
+// Thousands of building-sized polygons followed by a few large boundaries,
+// so that halving the number of features gives very unequal halves.
+fn skewed_collection(c: &mut Criterion) {
+    let feature = |value| Feature {
+        bbox: None,
+        geometry: Some(Geometry::new(value)),
+        id: None,
+        properties: None,
+        foreign_members: None,
+    };
+    let mut features: Vec<Feature> = (0..20_000)
+        .map(|i| feature(Value::Polygon(vec![ring(5, i as f64 * 0.001, 0.0)])))
+        .collect();
+    features.extend((0..4).map(|i| feature(Value::Polygon(vec![ring(250_000, i as f64, 1.0)]))));
+    let fc = FeatureCollection { bbox: None, features, foreign_members: None };
+
+    let mut group = c.benchmark_group("FeatureCollection 20k small + 4 x 250k vertices");
+    group.sample_size(20);
+    for &(label, split_by) in &[("split by elements", SplitBy::Elements), ("split by vertices", SplitBy::Vertices)] {
+        let options = BboxOptions::default().with_split_by(split_by);
+        group.bench_function(label, |b| b.iter(|| fc.to_bbox_with(&options).unwrap()));
+    }
+    group.finish();
+}
+
+
+criterion_group!(benches, line_string, multi_polygon, skewed_collection, strategies);
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+#[macro_use]
+extern crate criterion;
+extern crate geojson;
+extern crate par_bbox;
+
+use criterion::Criterion;
+use geojson::GeoJson;
+use par_bbox::{ToBbox, par_scan_bbox, scan_bbox};
+
+
+// A FeatureCollection of `n` small polygons with a few properties each, like
+// an OSM buildings export.
+fn buildings(n: usize) -> String {
+    let features: Vec<String> = (0..n)
+        .map(|i| {
+            let (x, y) = (-71.0 + (i % 1000) as f64 * 1e-4, 42.0 + (i / 1000) as f64 * 1e-4);
+            let ring: Vec<String> = (0..20)
+                .map(|j| {
+                    let t = j as f64 / 19.0 * 2.0 * std::f64::consts::PI;
+                    format!("[{},{}]", x + 3e-5 * t.cos(), y + 3e-5 * t.sin())
+                })
+                .collect();
+            format!(r#"{{"type":"Feature","properties":{{"osm_id":{},"building":"yes","name":"Building {}"}},"geometry":{{"type":"Polygon","coordinates":[[{}]]}}}}"#,
+                    i, i, ring.join(","))
+        })
+        .collect();
+    format!(r#"{{"type":"FeatureCollection","features":[{}]}}"#, features.join(","))
+}
+
+
+fn parse_then_bbox(c: &mut Criterion) {
+    let data = buildings(20_000);
+    let mut group = c.benchmark_group("20k buildings");
+    group.sample_size(10);
+    group.bench_function("parse then to_bbox", |b| b.iter(|| {
+        let geojson: GeoJson = data.parse().unwrap();
+        geojson.to_bbox().unwrap()
+    }));
+    group.bench_function("scan_bbox", |b| b.iter(|| scan_bbox(data.as_bytes()).unwrap()));
+    group.bench_function("par_scan_bbox", |b| b.iter(|| par_scan_bbox(data.as_bytes()).unwrap()));
+    group.finish();
+}
+
+
+criterion_group!(benches, parse_then_bbox);
+criterion_main!(benches);
@@ -0,0 +1,305 @@
+use std::f64;
+use std::fmt;
+
+
+/// An axis-aligned bounding box in longitude (x), latitude (y) and,
+/// optionally, elevation (z).
+///
+/// Only positions with a third element contribute to the z range, so for
+/// mixed 2D and 3D input zmin/zmax cover the 3D positions alone and x/y cover
+/// everything. A bbox with no 3D positions has an empty z range (`has_z` is
+/// false) and `is_mixed_dimension` reports whether both kinds were seen.
+///
+/// `Bbox::empty()` is the bounding box of nothing at all. It is the identity
+/// for `merge`, so merging any bbox with it returns that bbox unchanged.
+///
+/// Alongside the extents, a Bbox tracks its longitude ranges east and west
+/// of four cut meridians: the antimeridian and the meridians at -90, 0 and
+/// 90. Each cut gives a candidate interval, the smallest one not crossing
+/// it, and the narrowest candidate covers every point in the smallest
+/// interval, which may cross the antimeridian, while `merge` remains a
+/// plain min/max of every field and so stays associative. See
+/// `antimeridian`. The extents are derived from those ranges, so they are
+/// read with `xmin()` and the like, and a different bbox is made with
+/// `Bbox::new`.
+#[derive(Clone, Copy)]
+pub struct Bbox {
+    xmin: f64,
+    xmax: f64,
+    ymin: f64,
+    ymax: f64,
+    zmin: f64,
+    zmax: f64,
+
+    // The longitudes either side of each of CUTS.
+    splits: [Split; 4],
+    // Whether xmin/xmax may wrap across the antimeridian.
+    wrap: bool,
+    // Whether any 2D position (or 2D bbox) contributed.
+    has_2d: bool,
+}
+
+
+impl Bbox {
+    /// Arguments follow the RFC 7946 ordering of a "bbox" member:
+    /// `[xmin, ymin, xmax, ymax]`. If `xmin > xmax` the bbox crosses the
+    /// antimeridian, as allowed by RFC 7946 section 5.2. A crossing bbox
+    /// wider than 270 degrees may come out as the whole [-180, 180], see
+    /// `antimeridian`.
+    pub fn new(xmin: f64, ymin: f64, xmax: f64, ymax: f64) -> Self {
+        // An interval crossing the antimeridian covers xmin..180 and
+        // -180..xmax.
+        let wrap = xmin > xmax;
+        let pieces: &[(f64, f64)] = if wrap { &[(xmin, 180.0), (-180.0, xmax)] } else { &[(xmin, xmax)] };
+        let mut splits = [Split::EMPTY; 4];
+        for (split, &cut) in splits.iter_mut().zip(CUTS.iter()) {
+            for &(lo, hi) in pieces {
+                if hi < cut {
+                    split.west = merge_range(split.west, (lo, hi));
+                } else if lo >= cut {
+                    split.east = merge_range(split.east, (lo, hi));
+                } else {
+                    split.west = merge_range(split.west, (lo, cut));
+                    split.east = merge_range(split.east, (cut, hi));
+                }
+            }
+        }
+        Bbox {
+            xmin,
+            xmax,
+            ymin,
+            ymax,
+            zmin: f64::INFINITY,
+            zmax: f64::NEG_INFINITY,
+            splits,
+            wrap,
+            has_2d: true,
+        }.with_longitudes()
+    }
+
+    /// A 3D bbox, with arguments in the RFC 7946 ordering
+    /// `[xmin, ymin, zmin, xmax, ymax, zmax]`.
+    pub fn new_3d(xmin: f64, ymin: f64, zmin: f64, xmax: f64, ymax: f64, zmax: f64) -> Self {
+        Bbox { zmin, zmax, has_2d: false, ..Bbox::new(xmin, ymin, xmax, ymax) }
+    }
+
+    /// The bounding box of a single point.
+    pub fn from_point(x: f64, y: f64) -> Self {
+        let mut splits = [Split::EMPTY; 4];
+        for (split, &cut) in splits.iter_mut().zip(CUTS.iter()) {
+            if x < cut { split.west = (x, x) } else { split.east = (x, x) }
+        }
+        Bbox {
+            xmin: x,
+            xmax: x,
+            ymin: y,
+            ymax: y,
+            zmin: f64::INFINITY,
+            zmax: f64::NEG_INFINITY,
+            splits,
+            wrap: false,
+            has_2d: true,
+        }
+    }
+
+    /// The bounding box of a single 3D point.
+    pub fn from_point_3d(x: f64, y: f64, z: f64) -> Self {
+        Bbox { zmin: z, zmax: z, has_2d: false, ..Bbox::from_point(x, y) }
+    }
+
+    /// The empty bounding box. Its minimums are +infinity and maximums are
+    /// -infinity so that min/max with any other bbox yields the other bbox.
+    pub fn empty() -> Self {
+        Bbox {
+            xmin: f64::INFINITY,
+            xmax: f64::NEG_INFINITY,
+            ymin: f64::INFINITY,
+            ymax: f64::NEG_INFINITY,
+            zmin: f64::INFINITY,
+            zmax: f64::NEG_INFINITY,
+            splits: [Split::EMPTY; 4],
+            wrap: false,
+            has_2d: false,
+        }
+    }
+
+    pub fn xmin(&self) -> f64 { self.xmin }
+
+    pub fn xmax(&self) -> f64 { self.xmax }
+
+    pub fn ymin(&self) -> f64 { self.ymin }
+
+    pub fn ymax(&self) -> f64 { self.ymax }
+
+    /// The minimum z, +infinity if no 3D position contributed. See `z_range`.
+    pub fn zmin(&self) -> f64 { self.zmin }
+
+    /// The maximum z, -infinity if no 3D position contributed.
+    pub fn zmax(&self) -> f64 { self.zmax }
+
+    /// True if no coordinates contributed to this bbox. Only latitude is
+    /// checked since an antimeridian-crossing bbox has xmin > xmax.
+    pub fn is_empty(&self) -> bool {
+        self.ymin > self.ymax
+    }
+
+    /// True if any 3D position contributed, i.e. zmin/zmax are set.
+    pub fn has_z(&self) -> bool {
+        self.zmin <= self.zmax
+    }
+
+    /// The z range, if any 3D position contributed.
+    pub fn z_range(&self) -> Option<(f64, f64)> {
+        if self.has_z() { Some((self.zmin, self.zmax)) } else { None }
+    }
+
+    /// True if both 2D and 3D positions contributed. The z range then only
+    /// describes the 3D positions.
+    pub fn is_mixed_dimension(&self) -> bool {
+        self.has_2d && self.has_z()
+    }
+
+    /// A copy of this bbox whose longitudes are the smallest interval
+    /// covering every point, which may cross the antimeridian. In that case
+    /// `xmin > xmax` as in RFC 7946 section 5.2.
+    ///
+    /// The interval is the narrowest of those not crossing the meridians at
+    /// -90, 0 and 90, and the ordinary [xmin, xmax], which is preferred on a
+    /// tie. This finds the smallest interval
+    /// whenever it leaves out one of those meridians, which is always true
+    /// of intervals narrower than 270 degrees.
+    ///
+    /// The setting carries through `merge`: merging with an antimeridian
+    /// bbox gives an antimeridian bbox.
+    pub fn antimeridian(&self) -> Self {
+        Bbox { wrap: true, ..*self }.with_longitudes()
+    }
+
+    /// The bbox as an RFC 7946 "bbox" member: `[xmin, ymin, xmax, ymax]`,
+    /// or `[xmin, ymin, zmin, xmax, ymax, zmax]` if it has a z range.
+    pub fn to_vec(&self) -> Vec<f64> {
+        if self.has_z() {
+            vec![self.xmin, self.ymin, self.zmin, self.xmax, self.ymax, self.zmax]
+        } else {
+            vec![self.xmin, self.ymin, self.xmax, self.ymax]
+        }
+    }
+
+    /// True if xmin/xmax wrap across the antimeridian.
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.xmin > self.xmax
+    }
+
+    pub fn merge(&self, other: &Bbox) -> Self {
+        let mut splits = self.splits;
+        for (split, other) in splits.iter_mut().zip(other.splits.iter()) {
+            split.east = merge_range(split.east, other.east);
+            split.west = merge_range(split.west, other.west);
+        }
+        Bbox {
+            ymin: self.ymin.min(other.ymin),
+            ymax: self.ymax.max(other.ymax),
+            zmin: self.zmin.min(other.zmin),
+            zmax: self.zmax.max(other.zmax),
+            splits,
+            wrap: self.wrap || other.wrap,
+            has_2d: self.has_2d || other.has_2d,
+            ..*self
+        }.with_longitudes()
+    }
+
+    // Set xmin/xmax from the ordinary interval or, if the bbox may wrap, the
+    // narrowest one.
+    fn with_longitudes(mut self) -> Self {
+        let (mut xmin, mut xmax, mut width) = self.splits[0].interval();
+        if self.wrap {
+            for split in &self.splits[1..] {
+                let (lo, hi, w) = split.interval();
+                if w < width {
+                    xmin = lo;
+                    xmax = hi;
+                    width = w;
+                }
+            }
+        }
+        self.xmin = xmin;
+        self.xmax = xmax;
+        self
+    }
+}
+
+
+// An empty longitude range, represented the same way as in Bbox::empty() so
+// that merging ignores it.
+const EMPTY_RANGE: (f64, f64) = (f64::INFINITY, f64::NEG_INFINITY);
+
+
+// The smallest range covering both.
+fn merge_range(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0.min(b.0), a.1.max(b.1))
+}
+
+
+// The meridians longitudes are split at. The first is the antimeridian, which
+// gives the ordinary interval. Every interval narrower than 270 degrees leaves
+// out at least one of them.
+const CUTS: [f64; 4] = [-180.0, -90.0, 0.0, 90.0];
+
+
+// The longitude ranges of a bbox east of a cut meridian, up to 180, and west
+// of it, from -180.
+#[derive(Clone, Copy)]
+struct Split {
+    east: (f64, f64),
+    west: (f64, f64),
+}
+
+
+impl Split {
+    const EMPTY: Split = Split { east: EMPTY_RANGE, west: EMPTY_RANGE };
+
+    // The smallest interval not crossing the cut, as xmin, xmax and its
+    // width. If there are longitudes on both sides it runs east from the cut
+    // across the antimeridian.
+    fn interval(&self) -> (f64, f64, f64) {
+        let (east, west) = (self.east, self.west);
+        if east.0 <= east.1 && west.0 <= west.1 {
+            (east.0, west.1, west.1 + 360.0 - east.0)
+        } else {
+            let (xmin, xmax) = merge_range(east, west);
+            (xmin, xmax, xmax - xmin)
+        }
+    }
+}
+
+
+impl Default for Bbox {
+    fn default() -> Self { Bbox::empty() }
+}
+
+
+// Two bboxes are equal if they cover the same extents. The bookkeeping
+// behind those extents is not compared.
+impl PartialEq for Bbox {
+    fn eq(&self, other: &Bbox) -> bool {
+        self.xmin == other.xmin && self.xmax == other.xmax &&
+            self.ymin == other.ymin && self.ymax == other.ymax &&
+            self.zmin == other.zmin && self.zmax == other.zmax
+    }
+}
+
+
+// The z range is only shown when there is one, so 2D output is unchanged.
+impl fmt::Debug for Bbox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut d = f.debug_struct("Bbox");
+        d.field("xmin", &self.xmin)
+            .field("xmax", &self.xmax)
+            .field("ymin", &self.ymin)
+            .field("ymax", &self.ymax);
+        if self.has_z() {
+            d.field("zmin", &self.zmin).field("zmax", &self.zmax);
+        }
+        d.finish()
+    }
+}
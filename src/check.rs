@@ -0,0 +1,179 @@
+use std::f64;
+
+use rayon::prelude::*;
+
+use geojson::{Feature, GeoJson, Geometry, Value};
+
+use bbox::Bbox;
+use error::{BboxError, JsonPath};
+use to_bbox::ToBbox;
+
+
+/// A declared "bbox" member that differs from the computed extent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// Path to the bbox member itself, e.g. `features[3].bbox`.
+    pub path: JsonPath,
+    pub declared: Vec<f64>,
+    pub computed: Bbox,
+    /// The largest absolute difference between corresponding extents.
+    /// Infinite if the declared bbox is malformed, has a z range the object
+    /// lacks or the object has no coordinates to compare against, see
+    /// `bbox_difference`.
+    pub difference: f64,
+}
+
+
+/// The result of checking every bbox member in a GeoJSON object.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CheckReport {
+    /// How many bbox members were present and checked.
+    pub checked: usize,
+    /// Every checked member that differs at all, in document order.
+    pub mismatches: Vec<Mismatch>,
+}
+
+
+impl CheckReport {
+    /// The mismatches whose difference is larger than `tolerance`.
+    pub fn exceeding(&self, tolerance: f64) -> Vec<&Mismatch> {
+        self.mismatches.iter().filter(|m| m.difference > tolerance).collect()
+    }
+
+    fn merge(mut self, other: CheckReport) -> CheckReport {
+        self.checked += other.checked;
+        self.mismatches.extend(other.mismatches);
+        self
+    }
+}
+
+
+/// Compare every "bbox" member, on FeatureCollections, Features and
+/// Geometries, with the extent computed by `ToBbox`. Features are checked in
+/// parallel. A declared bbox that crosses the antimeridian is compared with
+/// the antimeridian-aware extent.
+pub fn check_bbox_members(geojson: &GeoJson) -> Result<CheckReport, BboxError> {
+    let root = JsonPath::default();
+    let (report, _) = match *geojson {
+        GeoJson::Geometry(ref geometry) => check_geometry(geometry, &root)?,
+        GeoJson::Feature(ref feature) => check_feature(feature, &root)?,
+        GeoJson::FeatureCollection(ref fc) => {
+            let features = root.join_field("features");
+            let results: Vec<Result<(CheckReport, Bbox), BboxError>> = fc.features.par_iter()
+                .enumerate()
+                .map(|(index, feature)| {
+                    check_feature(feature, &features.join_index(index))
+                        .map_err(|e| e.at_index(index).in_field("features"))
+                })
+                .collect();
+
+            let mut report = CheckReport::default();
+            let mut total = Bbox::empty();
+            for result in results {
+                let (r, bbox) = result?;
+                report = report.merge(r);
+                total = total.merge(&bbox);
+            }
+            (check_member(fc.bbox.as_ref(), &total, &root).merge(report), total)
+        }
+    };
+    Ok(report)
+}
+
+
+fn check_feature(feature: &Feature, path: &JsonPath) -> Result<(CheckReport, Bbox), BboxError> {
+    let (report, bbox) = match feature.geometry {
+        Some(ref geometry) => {
+            check_geometry(geometry, &path.join_field("geometry"))
+                .map_err(|e| e.in_field("geometry"))?
+        }
+        None => (CheckReport::default(), Bbox::empty()),
+    };
+    Ok((check_member(feature.bbox.as_ref(), &bbox, path).merge(report), bbox))
+}
+
+
+fn check_geometry(geometry: &Geometry, path: &JsonPath) -> Result<(CheckReport, Bbox), BboxError> {
+    let (report, bbox) = match geometry.value {
+        Value::GeometryCollection(ref geoms) => {
+            let geometries = path.join_field("geometries");
+            let mut report = CheckReport::default();
+            let mut bbox = Bbox::empty();
+            for (index, g) in geoms.iter().enumerate() {
+                let (r, b) = check_geometry(g, &geometries.join_index(index))
+                    .map_err(|e| e.at_index(index).in_field("geometries"))?;
+                report = report.merge(r);
+                bbox = bbox.merge(&b);
+            }
+            (report, bbox)
+        }
+        ref value => (CheckReport::default(), value.to_bbox()?),
+    };
+    Ok((check_member(geometry.bbox.as_ref(), &bbox, path).merge(report), bbox))
+}
+
+
+// Check the bbox member of the object at `path`, if it has one.
+fn check_member(declared: Option<&Vec<f64>>, computed: &Bbox, path: &JsonPath) -> CheckReport {
+    let declared = match declared {
+        Some(declared) => declared,
+        None => return CheckReport::default(),
+    };
+
+    let difference = bbox_difference(declared, computed);
+    let mismatches = if difference > 0.0 {
+        vec![Mismatch {
+            path: path.join_field("bbox"),
+            declared: declared.clone(),
+            computed: *computed,
+            difference,
+        }]
+    } else {
+        vec![]
+    };
+    CheckReport { checked: 1, mismatches }
+}
+
+
+/// The largest absolute difference between a declared bbox, as in a "bbox"
+/// member, and a computed one. A declared z range is compared with the
+/// computed one, and the difference is infinite if there is none, since the
+/// declared bbox then has a dimension the object lacks. The difference is
+/// also infinite if the declared bbox is malformed or the computed one is
+/// empty, unless the declared one is empty too: all zeros or NaN, as
+/// in the header of a Shapefile without shapes, or with ymin > ymax.
+pub fn bbox_difference(declared: &[f64], computed: &Bbox) -> f64 {
+    if computed.is_empty() {
+        return if declares_nothing(declared) { 0.0 } else { f64::INFINITY };
+    }
+    let (declared_xy, declared_z) = match declared.len() {
+        4 => ([declared[0], declared[1], declared[2], declared[3]], None),
+        6 => ([declared[0], declared[1], declared[3], declared[4]], Some((declared[2], declared[5]))),
+        _ => return f64::INFINITY,
+    };
+
+    let computed = if declared_xy[0] > declared_xy[2] { computed.antimeridian() } else { *computed };
+    let computed_xy = [computed.xmin(), computed.ymin(), computed.xmax(), computed.ymax()];
+    let difference = declared_xy.iter().zip(computed_xy.iter())
+        .map(|(d, c)| (d - c).abs())
+        .fold(0.0, f64::max);
+
+    match (declared_z, computed.z_range()) {
+        (Some((zmin, zmax)), Some((czmin, czmax))) => {
+            difference.max((zmin - czmin).abs()).max((zmax - czmax).abs())
+        }
+        (Some(_), None) => f64::INFINITY,
+        (None, _) => difference,
+    }
+}
+
+
+// Whether a declared bbox is the extent of nothing at all.
+fn declares_nothing(declared: &[f64]) -> bool {
+    let inverted = match declared.len() {
+        4 => declared[1] > declared[3],
+        6 => declared[1] > declared[4],
+        _ => return false,
+    };
+    inverted || declared.iter().all(|&v| v == 0.0 || v.is_nan())
+}
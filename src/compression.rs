@@ -0,0 +1,50 @@
+use std::io::{self, Cursor, Read};
+
+use flate2::read::MultiGzDecoder;
+use zstd::Decoder as ZstdDecoder;
+
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+
+/// A compression format of the input, recognized by its magic bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+
+impl Compression {
+    /// The compression of data starting with these bytes, or None if it
+    /// isn't compressed.
+    pub fn detect(start: &[u8]) -> Option<Compression> {
+        if start.starts_with(GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if start.starts_with(ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+}
+
+
+/// Wrap a reader so that gzip or zstd input is decompressed as it is read.
+/// Anything else is passed through unchanged.
+///
+/// Concatenated gzip members, as written by `pigz` or by appending to a
+/// `.gz` file, are read as one stream, as are several zstd frames.
+pub fn decompress<'a, R: Read + Send + 'a>(mut reader: R) -> io::Result<Box<dyn Read + Send + 'a>> {
+    // Read the magic bytes, then put them back in front of the rest.
+    let mut start = Vec::with_capacity(ZSTD_MAGIC.len());
+    (&mut reader).take(ZSTD_MAGIC.len() as u64).read_to_end(&mut start)?;
+    let compression = Compression::detect(&start);
+    let reader = Cursor::new(start).chain(reader);
+    Ok(match compression {
+        None => Box::new(reader),
+        Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(reader)),
+        Some(Compression::Zstd) => Box::new(ZstdDecoder::new(reader)?),
+    })
+}
@@ -0,0 +1,164 @@
+use std::io::Read;
+use std::str;
+
+use csv::{self, ByteRecord, StringRecord};
+use rayon;
+use rayon::prelude::*;
+
+use bbox::Bbox;
+use error::ReadError;
+use to_bbox::ToBbox;
+
+
+// Header names recognized as coordinate columns, in order of preference,
+// compared without regard to case.
+const LON_NAMES: &[&str] = &["lon", "lng", "long", "longitude", "x"];
+const LAT_NAMES: &[&str] = &["lat", "latitude", "y"];
+
+
+/// How `csv_bbox` finds the coordinates in a CSV file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// The header of the longitude column, or None to detect it from common
+    /// names such as "lon", "lng", "longitude" or "x".
+    pub lon: Option<String>,
+    /// The header of the latitude column, or None to detect it from "lat",
+    /// "latitude" or "y".
+    pub lat: Option<String>,
+    /// The byte between fields, a comma by default.
+    pub delimiter: u8,
+}
+
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions { lon: None, lat: None, delimiter: b',' }
+    }
+}
+
+
+/// The bounding box of the points in a CSV file, and the rows that did not
+/// contribute to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CsvPoints {
+    pub bbox: Bbox,
+    /// Rows with a point.
+    pub points: usize,
+    /// Rows with both coordinates blank, such as addresses a geocoder could
+    /// not place.
+    pub blank: usize,
+    /// Rows with a coordinate that is missing or not a finite number.
+    pub unparseable: usize,
+    /// The line number of the first unparseable row.
+    pub first_unparseable: Option<u64>,
+}
+
+
+impl CsvPoints {
+    fn empty() -> Self {
+        CsvPoints { bbox: Bbox::empty(), points: 0, blank: 0, unparseable: 0, first_unparseable: None }
+    }
+
+    fn merge(&self, other: &CsvPoints) -> CsvPoints {
+        let first_unparseable = match (self.first_unparseable, other.first_unparseable) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        CsvPoints {
+            bbox: self.bbox.merge(&other.bbox),
+            points: self.points + other.points,
+            blank: self.blank + other.blank,
+            unparseable: self.unparseable + other.unparseable,
+            first_unparseable,
+        }
+    }
+}
+
+
+/// Compute the bounding box of the points in a CSV file with a header row,
+/// one point per row in a longitude and a latitude column. The columns are
+/// named in `options` or detected from their headers.
+///
+/// Rows are read in chunks of `chunk_size`. Each chunk is parsed in parallel
+/// while the next one is read, every row's position becoming a bbox with
+/// `ToBbox`, and the bboxes are merged with `Bbox::merge`. Rows whose
+/// coordinates are both blank, or can't be parsed, are counted rather than
+/// failing the whole file. Only a file that can't be read, or in which the
+/// columns can't be found, is an error.
+pub fn csv_bbox<R: Read + Send>(reader: R, options: &CsvOptions, chunk_size: usize) -> Result<CsvPoints, ReadError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .flexible(true)
+        .from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let lon = column(&headers, options.lon.as_deref(), LON_NAMES, "longitude")?;
+    let lat = column(&headers, options.lat.as_deref(), LAT_NAMES, "latitude")?;
+
+    let chunk_size = chunk_size.max(1);
+    let mut total = CsvPoints::empty();
+    let mut chunk = read_chunk(&mut reader, chunk_size)?;
+    while !chunk.is_empty() {
+        let (next, points) = rayon::join(
+            || read_chunk(&mut reader, chunk_size),
+            || chunk_points(&chunk, lon, lat));
+        total = total.merge(&points);
+        chunk = next?;
+    }
+    Ok(total)
+}
+
+
+// The index of the named column, or of the first column with one of
+// `names` if none is given.
+fn column(headers: &StringRecord, name: Option<&str>, names: &[&str], what: &str) -> Result<usize, ReadError> {
+    let position = |name: &str| headers.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    match name {
+        Some(name) => position(name)
+            .ok_or_else(|| ReadError::Csv(format!("there is no column named '{}'", name))),
+        None => names.iter().filter_map(|name| position(name)).next()
+            .ok_or_else(|| {
+                let headers = headers.iter().collect::<Vec<&str>>().join(", ");
+                ReadError::Csv(format!("no {} column found among the headers: {}", what, headers))
+            }),
+    }
+}
+
+
+// Read up to chunk_size rows. Returns an empty chunk at the end of input.
+fn read_chunk<R: Read>(reader: &mut csv::Reader<R>, chunk_size: usize) -> Result<Vec<ByteRecord>, ReadError> {
+    let mut chunk = Vec::with_capacity(chunk_size);
+    let mut record = ByteRecord::new();
+    while chunk.len() < chunk_size && reader.read_byte_record(&mut record)? {
+        chunk.push(record.clone());
+    }
+    Ok(chunk)
+}
+
+
+fn chunk_points(chunk: &[ByteRecord], lon: usize, lat: usize) -> CsvPoints {
+    chunk.par_iter()
+        .map(|record| row_points(record, lon, lat))
+        .reduce(CsvPoints::empty, |a, b| a.merge(&b))
+}
+
+
+fn row_points(record: &ByteRecord, lon: usize, lat: usize) -> CsvPoints {
+    let mut points = CsvPoints::empty();
+    let field = |i: usize| record.get(i).and_then(|f| str::from_utf8(f).ok()).map(str::trim);
+    match (field(lon), field(lat)) {
+        (Some(""), Some("")) => points.blank = 1,
+        (Some(x), Some(y)) => match (x.parse::<f64>(), y.parse::<f64>()) {
+            (Ok(x), Ok(y)) if x.is_finite() && y.is_finite() => {
+                // A two-element position always has a bbox.
+                points.bbox = vec![x, y].to_bbox().unwrap();
+                points.points = 1;
+            }
+            _ => points.unparseable = 1,
+        },
+        _ => points.unparseable = 1,
+    }
+    if points.unparseable > 0 {
+        points.first_unparseable = record.position().map(|p| p.line());
+    }
+    points
+}
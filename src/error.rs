@@ -0,0 +1,253 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str;
+
+use csv;
+use geojson;
+use serde_json;
+
+
+/// The reason a bounding box could not be computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BboxErrorKind {
+    /// A Position had fewer than the two elements needed for x and y. The
+    /// number of elements found is included.
+    ShortPosition(usize),
+}
+
+
+impl fmt::Display for BboxErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BboxErrorKind::ShortPosition(n) =>
+                write!(f, "position has {} element(s), expected at least 2", n),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Field(&'static str),
+    Index(usize),
+}
+
+
+/// The location of an element within a GeoJSON document, written the way
+/// it would be accessed from the document root, e.g.
+/// `features[1234].geometry.coordinates[0]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+
+impl JsonPath {
+    // The path of a member of the object at this path.
+    pub(crate) fn join_field(&self, name: &'static str) -> JsonPath {
+        let mut path = self.clone();
+        path.segments.push(Segment::Field(name));
+        path
+    }
+
+    // The path of an element of the array at this path.
+    pub(crate) fn join_index(&self, index: usize) -> JsonPath {
+        let mut path = self.clone();
+        path.segments.push(Segment::Index(index));
+        path
+    }
+}
+
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match *segment {
+                Segment::Field(name) if i == 0 => write!(f, "{}", name)?,
+                Segment::Field(name) => write!(f, ".{}", name)?,
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// An error computing a bounding box, along with the path to the offending
+/// element.
+///
+/// Errors are created where the problem is found, with an empty path, and
+/// each enclosing object prepends its own segment as the error travels back
+/// up the call stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BboxError {
+    kind: BboxErrorKind,
+    path: JsonPath,
+}
+
+
+impl BboxError {
+    pub fn new(kind: BboxErrorKind) -> Self {
+        BboxError { kind, path: JsonPath::default() }
+    }
+
+    pub fn kind(&self) -> BboxErrorKind { self.kind }
+
+    pub fn path(&self) -> &JsonPath { &self.path }
+
+    /// Prepend an object member name to the error's path.
+    pub fn in_field(mut self, name: &'static str) -> Self {
+        self.path.segments.insert(0, Segment::Field(name));
+        self
+    }
+
+    /// Prepend an array index to the error's path.
+    pub fn at_index(mut self, index: usize) -> Self {
+        self.path.segments.insert(0, Segment::Index(index));
+        self
+    }
+}
+
+
+impl fmt::Display for BboxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.segments.is_empty() {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "{} at {}", self.kind, self.path)
+        }
+    }
+}
+
+
+impl Error for BboxError {}
+
+
+/// An error reading input and computing its bounding box.
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    /// The input is not valid JSON.
+    Json(serde_json::Error),
+    /// The input is not UTF-8, with the offset of the first invalid byte and
+    /// the JSON error it caused.
+    NotUtf8(usize, serde_json::Error),
+    /// The input ends in the middle of a JSON text.
+    Truncated(serde_json::Error),
+    /// The input is empty, or only whitespace, where JSON was expected.
+    Empty,
+    /// The input is valid JSON but not valid GeoJSON.
+    GeoJson(geojson::Error),
+    /// The input is not a valid Shapefile.
+    Shapefile(String),
+    /// The input is not a valid FlatGeobuf file.
+    FlatGeobuf(String),
+    /// The input is not valid CSV, or has no coordinate columns.
+    Csv(String),
+    /// The input is not valid WKT, or WKB.
+    Wkt(String),
+    Wkb(String),
+    Bbox(BboxError),
+    /// An error in one record of a GeoJSON text sequence, with the record's
+    /// 1-based number.
+    Record(usize, Box<ReadError>),
+}
+
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReadError::Io(ref e) => write!(f, "{}", e),
+            ReadError::Json(ref e) => write!(f, "invalid JSON: {}", e),
+            ReadError::NotUtf8(offset, ref e) =>
+                write!(f, "input is not UTF-8: invalid byte at offset {} ({})", offset, e),
+            ReadError::Truncated(ref e) => write!(f, "input ends early, is it truncated? ({})", e),
+            ReadError::Empty => write!(f, "input is empty"),
+            ReadError::GeoJson(ref e) => write!(f, "invalid GeoJSON: {}", e),
+            ReadError::Shapefile(ref message) => write!(f, "invalid Shapefile: {}", message),
+            ReadError::FlatGeobuf(ref message) => write!(f, "invalid FlatGeobuf: {}", message),
+            ReadError::Csv(ref message) => write!(f, "invalid CSV: {}", message),
+            ReadError::Wkt(ref message) => write!(f, "invalid WKT: {}", message),
+            ReadError::Wkb(ref message) => write!(f, "invalid WKB: {}", message),
+            ReadError::Bbox(ref e) => write!(f, "{}", e),
+            ReadError::Record(number, ref e) => write!(f, "record {}: {}", number, e),
+        }
+    }
+}
+
+
+impl Error for ReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ReadError::Io(ref e) => Some(e),
+            ReadError::Json(ref e) | ReadError::NotUtf8(_, ref e) | ReadError::Truncated(ref e) => Some(e),
+            ReadError::Empty => None,
+            ReadError::GeoJson(ref e) => Some(e),
+            ReadError::Shapefile(_) | ReadError::FlatGeobuf(_) | ReadError::Csv(_) | ReadError::Wkt(_)
+            | ReadError::Wkb(_) => None,
+            ReadError::Bbox(ref e) => Some(e),
+            ReadError::Record(_, ref e) => Some(&**e),
+        }
+    }
+}
+
+
+impl ReadError {
+    /// The error for a JSON error in parsing `data`. Input that isn't UTF-8
+    /// or that ends early, which serde_json only reports as an invalid code
+    /// point or an unexpected EOF, gets an error of its own.
+    pub fn from_json(data: &[u8], e: serde_json::Error) -> ReadError {
+        let not_utf8 = str::from_utf8(data).err().map(|utf8| utf8.valid_up_to());
+        ReadError::json_error(e, not_utf8, data.iter().all(u8::is_ascii_whitespace))
+    }
+
+    // The same for input that was read rather than held in memory, given
+    // the offset of its first invalid UTF-8 sequence, if any, and whether it
+    // was only whitespace.
+    pub(crate) fn json_error(e: serde_json::Error, not_utf8: Option<usize>, blank: bool) -> ReadError {
+        if let Some(offset) = not_utf8 {
+            ReadError::NotUtf8(offset, e)
+        } else if !e.is_eof() {
+            ReadError::Json(e)
+        } else if blank {
+            ReadError::Empty
+        } else {
+            ReadError::Truncated(e)
+        }
+    }
+}
+
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> Self { ReadError::Io(e) }
+}
+
+
+impl From<serde_json::Error> for ReadError {
+    fn from(e: serde_json::Error) -> Self { ReadError::Json(e) }
+}
+
+
+// Errors reading the file stay I/O errors.
+impl From<csv::Error> for ReadError {
+    fn from(e: csv::Error) -> Self {
+        if !e.is_io_error() {
+            return ReadError::Csv(e.to_string());
+        }
+        match e.into_kind() {
+            csv::ErrorKind::Io(e) => ReadError::Io(e),
+            _ => unreachable!(),
+        }
+    }
+}
+
+
+impl From<geojson::Error> for ReadError {
+    fn from(e: geojson::Error) -> Self { ReadError::GeoJson(e) }
+}
+
+
+impl From<BboxError> for ReadError {
+    fn from(e: BboxError) -> Self { ReadError::Bbox(e) }
+}
@@ -0,0 +1,114 @@
+use std::io::{self, Write};
+
+use rayon::prelude::*;
+use serde_json::{self, Value as JsonValue};
+
+use geojson::{Feature, FeatureCollection};
+
+use bbox::Bbox;
+use error::BboxError;
+use to_bbox::ToBbox;
+
+
+/// What to use as the identifier of each feature in per-feature output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeatureKey {
+    /// The Feature's "id" member.
+    Id,
+    /// A member of the Feature's "properties", e.g. "osm_id".
+    Property(String),
+}
+
+
+impl FeatureKey {
+    // The key's value for a feature, if it has one. Strings are used as-is
+    // and any other JSON value is written out as JSON.
+    fn of(&self, feature: &Feature) -> Option<String> {
+        let value = match *self {
+            FeatureKey::Id => feature.id.as_ref(),
+            FeatureKey::Property(ref name) => {
+                feature.properties.as_ref().and_then(|p| p.get(name))
+            }
+        };
+        match value {
+            None | Some(&JsonValue::Null) => None,
+            Some(JsonValue::String(s)) => Some(s.clone()),
+            Some(v) => Some(v.to_string()),
+        }
+    }
+}
+
+
+/// The bounding box of one feature of a FeatureCollection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureBbox {
+    /// Position of the feature in the "features" array.
+    pub index: usize,
+    pub id: Option<String>,
+    pub bbox: Bbox,
+}
+
+
+/// Compute the bounding box of every feature in parallel, in feature order.
+/// Features without coordinates get an empty bbox. If any feature fails, the
+/// error for the lowest feature index is returned.
+pub fn feature_bboxes(fc: &FeatureCollection, key: &FeatureKey) -> Result<Vec<FeatureBbox>, BboxError> {
+    let results: Vec<Result<FeatureBbox, BboxError>> = fc.features.par_iter()
+        .enumerate()
+        .map(|(index, feature)| {
+            let bbox = feature.to_bbox()
+                .map_err(|e| e.at_index(index).in_field("features"))?;
+            Ok(FeatureBbox { index, id: key.of(feature), bbox })
+        })
+        .collect();
+    results.into_iter().collect()
+}
+
+
+/// Write one CSV row per feature, with a header:
+/// `index,id,xmin,ymin,xmax,ymax`. Missing ids and the extents of empty
+/// bboxes are left blank.
+pub fn write_csv<W: Write>(rows: &[FeatureBbox], mut out: W) -> io::Result<()> {
+    writeln!(out, "index,id,xmin,ymin,xmax,ymax")?;
+    for row in rows {
+        let id = row.id.as_ref().map_or(String::new(), |id| csv_field(id));
+        if row.bbox.is_empty() {
+            writeln!(out, "{},{},,,,", row.index, id)?;
+        } else {
+            writeln!(out, "{},{},{},{},{},{}", row.index, id,
+                     row.bbox.xmin(), row.bbox.ymin(), row.bbox.xmax(), row.bbox.ymax())?;
+        }
+    }
+    Ok(())
+}
+
+
+/// Write one JSON object per line, e.g.
+/// `{"index":0,"id":"a","xmin":0.0,"ymin":0.0,"xmax":1.0,"ymax":1.0}`.
+/// Missing ids and the extents of empty bboxes are null.
+pub fn write_ndjson<W: Write>(rows: &[FeatureBbox], mut out: W) -> io::Result<()> {
+    for row in rows {
+        let extent = |v: f64| if row.bbox.is_empty() { JsonValue::Null } else { json!(v) };
+        let line = json!({
+            "index": row.index,
+            "id": row.id,
+            "xmin": extent(row.bbox.xmin()),
+            "ymin": extent(row.bbox.ymin()),
+            "xmax": extent(row.bbox.xmax()),
+            "ymax": extent(row.bbox.ymax()),
+        });
+        serde_json::to_writer(&mut out, &line)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+
+// Quote a CSV field if it contains a delimiter, quote or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
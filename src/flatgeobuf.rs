@@ -0,0 +1,981 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::str;
+
+use flatbuffers::{FlatBufferBuilder, Follow, ForwardsUOffset, InvalidFlatbuffer, Table, TableFinishedWIPOffset,
+                  Verifiable, Vector, Verifier, WIPOffset};
+use rayon::prelude::*;
+use serde_json::{self, Map, Value as JsonValue};
+
+use geojson::{Feature, FeatureCollection, Geometry, Position, Value};
+
+use bbox::Bbox;
+use error::{BboxError, BboxErrorKind, ReadError};
+use features::{FeatureKey, feature_bboxes};
+
+
+const MAGIC: &[u8] = b"fgb\x03fgb\x00";
+const NODE_ITEM_LEN: usize = 40;
+
+/// The number of children of each node of the index `write_flatgeobuf`
+/// builds, FlatGeobuf's default.
+pub const DEFAULT_NODE_SIZE: u16 = 16;
+
+// Centres of bboxes are mapped onto a 2^16 x 2^16 grid along the Hilbert
+// curve, as the reference implementations do.
+const HILBERT_MAX: f64 = 65535.0;
+
+// Geometry types, from the FlatGeobuf schema. The curve and surface types
+// that follow GeometryCollection have no GeoJSON equivalent.
+const UNKNOWN: u8 = 0;
+const POINT: u8 = 1;
+const LINESTRING: u8 = 2;
+const POLYGON: u8 = 3;
+const MULTIPOINT: u8 = 4;
+const MULTILINESTRING: u8 = 5;
+const MULTIPOLYGON: u8 = 6;
+const GEOMETRY_COLLECTION: u8 = 7;
+
+// Column types, from the FlatGeobuf schema.
+const BYTE: u8 = 0;
+const UBYTE: u8 = 1;
+const BOOL: u8 = 2;
+const SHORT: u8 = 3;
+const USHORT: u8 = 4;
+const INT: u8 = 5;
+const UINT: u8 = 6;
+const LONG: u8 = 7;
+const ULONG: u8 = 8;
+const FLOAT: u8 = 9;
+const DOUBLE: u8 = 10;
+const STRING: u8 = 11;
+const JSON: u8 = 12;
+const DATETIME: u8 = 13;
+const BINARY: u8 = 14;
+
+// Field indexes of the tables we read and write, in schema order.
+const HEADER_ENVELOPE: u16 = 1;
+const HEADER_GEOMETRY_TYPE: u16 = 2;
+const HEADER_HAS_Z: u16 = 3;
+const HEADER_COLUMNS: u16 = 7;
+const HEADER_FEATURES_COUNT: u16 = 8;
+const HEADER_INDEX_NODE_SIZE: u16 = 9;
+const HEADER_CRS: u16 = 10;
+const CRS_CODE: u16 = 1;
+const COLUMN_NAME: u16 = 0;
+const COLUMN_TYPE: u16 = 1;
+const FEATURE_GEOMETRY: u16 = 0;
+const FEATURE_PROPERTIES: u16 = 1;
+const FEATURE_COLUMNS: u16 = 2;
+const GEOMETRY_ENDS: u16 = 0;
+const GEOMETRY_XY: u16 = 1;
+const GEOMETRY_Z: u16 = 2;
+const GEOMETRY_TYPE: u16 = 6;
+const GEOMETRY_PARTS: u16 = 7;
+
+
+/// Read a FlatGeobuf file. See `parse_flatgeobuf`.
+pub fn read_flatgeobuf<P: AsRef<Path>>(path: P) -> Result<FeatureCollection, ReadError> {
+    parse_flatgeobuf(&fs::read(path)?)
+}
+
+
+/// Parse a FlatGeobuf file into a FeatureCollection, decoding the features
+/// in parallel. Column values become the features' properties, and
+/// geometries with z values keep them.
+///
+/// The extent declared in the header is kept as the FeatureCollection's
+/// "bbox" member, falling back to the root of the spatial index, and each
+/// feature's entry in the index as its Feature's, so that
+/// `check_bbox_members` compares them with the computed extents.
+pub fn parse_flatgeobuf(data: &[u8]) -> Result<FeatureCollection, ReadError> {
+    if data.len() < MAGIC.len() + 4 || data[..3] != MAGIC[..3] || data[4..7] != MAGIC[4..7] {
+        return Err(invalid("not a FlatGeobuf file"));
+    }
+    if data[3] != MAGIC[3] {
+        return Err(invalid(&format!("unsupported version {}", data[3])));
+    }
+    // The header, like each feature, is a size-prefixed flatbuffer.
+    let header_len = 4 + le_u32(data, MAGIC.len())?;
+    let header_data = bytes(data, MAGIC.len(), header_len)?;
+    let header = flatbuffers::size_prefixed_root::<HeaderTable>(header_data).map_err(invalid_flatbuffer)?;
+
+    let geometry_type = header.scalar(HEADER_GEOMETRY_TYPE, UNKNOWN);
+    let has_z = header.scalar(HEADER_HAS_Z, false);
+    let columns = header.columns(HEADER_COLUMNS);
+    let count = header.scalar(HEADER_FEATURES_COUNT, 0u64) as usize;
+    let node_size = header.scalar(HEADER_INDEX_NODE_SIZE, DEFAULT_NODE_SIZE) as usize;
+
+    let index_start = MAGIC.len() + header_len;
+    let nodes = if node_size > 0 && count > 0 {
+        if node_size < 2 {
+            return Err(invalid("index node size must be at least 2"));
+        }
+        let num_nodes = level_sizes(count, node_size).iter().sum::<usize>();
+        let index = bytes(data, index_start, num_nodes.saturating_mul(NODE_ITEM_LEN))?;
+        index.chunks_exact(NODE_ITEM_LEN).map(NodeItem::read).collect()
+    } else {
+        Vec::new()
+    };
+
+    let features_start = index_start + nodes.len() * NODE_ITEM_LEN;
+    let records = walk_features(data, features_start)?;
+    if count > 0 && records.len() != count {
+        return Err(invalid(&format!("the header declares {} feature(s) but the file has {}", count, records.len())));
+    }
+
+    let results: Vec<Result<Feature, ReadError>> = records.par_iter()
+        .enumerate()
+        .map(|(index, &(offset, len))| {
+            parse_feature(&data[features_start + offset..features_start + offset + len], geometry_type, has_z, columns)
+                .map_err(|e| ReadError::Record(index + 1, Box::new(e)))
+        })
+        .collect();
+    let mut features = results.into_iter().collect::<Result<Vec<Feature>, ReadError>>()?;
+
+    // The leaves are the last `count` nodes, each with the offset of its
+    // feature from the start of the features.
+    if !nodes.is_empty() {
+        for node in &nodes[nodes.len() - count..] {
+            let index = records.binary_search_by_key(&node.offset, |&(offset, _)| offset as u64)
+                .map_err(|_| invalid(&format!("index entry points to offset {}, which starts no feature", node.offset)))?;
+            features[index].bbox = node.bbox();
+        }
+    }
+
+    let bbox = match header.get::<ForwardsUOffset<Vector<f64>>>(HEADER_ENVELOPE) {
+        Some(envelope) if !envelope.is_empty() => Some(envelope.iter().collect()),
+        _ => nodes.first().and_then(|root| root.bbox()),
+    };
+    Ok(FeatureCollection { bbox, features, foreign_members: None })
+}
+
+
+/// Write a FeatureCollection as FlatGeobuf, with a packed Hilbert R-tree
+/// index of the features' bboxes.
+///
+/// The bbox of every feature is computed in parallel with `ToBbox`, as by
+/// `feature_bboxes`, and the features are encoded in parallel. They are then
+/// sorted by the Hilbert value of their bbox centres and written in that
+/// order, which is what lets readers fetch just the features in a bbox with
+/// range requests. Properties become columns typed by the values found, and
+/// geometries keep their z values. Feature ids and foreign members are not
+/// written, FlatGeobuf having no place for them, and the CRS is declared as
+/// EPSG:4326, that of RFC 7946 GeoJSON.
+pub fn write_flatgeobuf<W: Write>(fc: &FeatureCollection, mut out: W) -> Result<(), ReadError> {
+    let bboxes = feature_bboxes(fc, &FeatureKey::Id)?;
+    let extent = bboxes.iter().fold(Bbox::empty(), |total, row| total.merge(&row.bbox));
+    let columns = infer_columns(&fc.features);
+    let has_z = extent.has_z();
+
+    let mut geometry_types = fc.features.iter()
+        .filter_map(|f| f.geometry.as_ref().map(|g| value_type(&g.value)));
+    let first = geometry_types.next().unwrap_or(UNKNOWN);
+    let geometry_type = if geometry_types.all(|t| t == first) { first } else { UNKNOWN };
+
+    // feature_bboxes only checks the outer rings of polygons, so the holes
+    // can still have short positions. The first error in feature order wins.
+    let encoded: Vec<Result<Vec<u8>, BboxError>> = fc.features.par_iter()
+        .enumerate()
+        .map(|(index, feature)| {
+            encode_feature(feature, &columns, has_z).map_err(|e| e.at_index(index).in_field("features"))
+        })
+        .collect();
+    let encoded = encoded.into_iter().collect::<Result<Vec<Vec<u8>>, BboxError>>()?;
+
+    // Descending order, as the reference implementations sort. Features
+    // without coordinates end up last, in their original order.
+    let mut order: Vec<usize> = (0..encoded.len()).collect();
+    order.par_sort_by_key(|&i| Reverse(hilbert_value(&bboxes[i].bbox, &extent)));
+
+    let mut leaves = Vec::with_capacity(order.len());
+    let mut offset = 0;
+    for &i in &order {
+        leaves.push(NodeItem::new(&bboxes[i].bbox, offset));
+        offset += encoded[i].len() as u64;
+    }
+    let nodes = if leaves.is_empty() { Vec::new() } else { build_index(leaves, DEFAULT_NODE_SIZE as usize) };
+
+    out.write_all(MAGIC)?;
+    out.write_all(&encode_header(&extent, geometry_type, has_z, &columns, order.len()))?;
+    for node in &nodes {
+        node.write(&mut out)?;
+    }
+    for &i in &order {
+        out.write_all(&encoded[i])?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+
+fn invalid(message: &str) -> ReadError {
+    ReadError::FlatGeobuf(message.to_string())
+}
+
+
+fn invalid_flatbuffer(e: InvalidFlatbuffer) -> ReadError {
+    invalid(&e.to_string())
+}
+
+
+fn bytes(data: &[u8], pos: usize, len: usize) -> Result<&[u8], ReadError> {
+    data.get(pos..pos.saturating_add(len)).ok_or_else(|| invalid("unexpected end of data"))
+}
+
+
+fn le_u32(data: &[u8], pos: usize) -> Result<usize, ReadError> {
+    Ok(u32::from_le_bytes(bytes(data, pos, 4)?.try_into().unwrap()) as usize)
+}
+
+
+// The offset from `start` and length of every size-prefixed feature,
+// including its size prefix.
+fn walk_features(data: &[u8], start: usize) -> Result<Vec<(usize, usize)>, ReadError> {
+    let mut records = Vec::new();
+    let mut pos = start;
+    while pos < data.len() {
+        let len = le_u32(data, pos)
+            .and_then(|len| bytes(data, pos, 4 + len).map(|_| 4 + len))
+            .map_err(|e| ReadError::Record(records.len() + 1, Box::new(e)))?;
+        records.push((pos - start, len));
+        pos += len;
+    }
+    Ok(records)
+}
+
+
+// A table of a verified FlatGeobuf buffer, whose fields are read by their
+// index in the schema.
+#[derive(Clone, Copy)]
+struct Fields<'a>(Table<'a>);
+
+
+impl<'a> Fields<'a> {
+    fn get<T: Follow<'a> + 'a>(&self, field: u16) -> Option<T::Inner> {
+        // Safety: every table is verified against the schema for the fields
+        // that are read, by the Verifiable implementations below, before
+        // any of it is read.
+        unsafe { self.0.get::<T>(flatbuffers::field_index_to_field_offset(field), None) }
+    }
+
+    fn scalar<T: Follow<'a, Inner = T> + 'a>(&self, field: u16, default: T) -> T {
+        self.get::<T>(field).unwrap_or(default)
+    }
+
+    fn columns(&self, field: u16) -> Option<Vector<'a, ForwardsUOffset<ColumnTable>>> {
+        self.get::<ForwardsUOffset<Vector<ForwardsUOffset<ColumnTable>>>>(field)
+    }
+}
+
+
+// Marker types for the tables of the FlatGeobuf schema, as `flatc` would
+// generate, which verify the fields we read and follow to `Fields`.
+struct HeaderTable;
+struct ColumnTable;
+struct FeatureTable;
+struct GeometryTable;
+
+
+macro_rules! follow_fields {
+    ($($table:ident),*) => {
+        $(
+            impl<'a> Follow<'a> for $table {
+                type Inner = Fields<'a>;
+
+                unsafe fn follow(buf: &'a [u8], loc: usize) -> Fields<'a> {
+                    Fields(Table::new(buf, loc))
+                }
+            }
+        )*
+    }
+}
+
+follow_fields!(HeaderTable, ColumnTable, FeatureTable, GeometryTable);
+
+
+type VerifyResult = Result<(), InvalidFlatbuffer>;
+
+
+impl Verifiable for HeaderTable {
+    fn run_verifier(v: &mut Verifier, pos: usize) -> VerifyResult {
+        v.visit_table(pos)?
+            .visit_field::<ForwardsUOffset<Vector<f64>>>("envelope", field(HEADER_ENVELOPE), false)?
+            .visit_field::<u8>("geometry_type", field(HEADER_GEOMETRY_TYPE), false)?
+            .visit_field::<bool>("has_z", field(HEADER_HAS_Z), false)?
+            .visit_field::<ForwardsUOffset<Vector<ForwardsUOffset<ColumnTable>>>>(
+                "columns", field(HEADER_COLUMNS), false)?
+            .visit_field::<u64>("features_count", field(HEADER_FEATURES_COUNT), false)?
+            .visit_field::<u16>("index_node_size", field(HEADER_INDEX_NODE_SIZE), false)?
+            .finish();
+        Ok(())
+    }
+}
+
+
+impl Verifiable for ColumnTable {
+    fn run_verifier(v: &mut Verifier, pos: usize) -> VerifyResult {
+        v.visit_table(pos)?
+            .visit_field::<ForwardsUOffset<&str>>("name", field(COLUMN_NAME), true)?
+            .visit_field::<u8>("type", field(COLUMN_TYPE), false)?
+            .finish();
+        Ok(())
+    }
+}
+
+
+impl Verifiable for FeatureTable {
+    fn run_verifier(v: &mut Verifier, pos: usize) -> VerifyResult {
+        v.visit_table(pos)?
+            .visit_field::<ForwardsUOffset<GeometryTable>>("geometry", field(FEATURE_GEOMETRY), false)?
+            .visit_field::<ForwardsUOffset<Vector<u8>>>("properties", field(FEATURE_PROPERTIES), false)?
+            .visit_field::<ForwardsUOffset<Vector<ForwardsUOffset<ColumnTable>>>>(
+                "columns", field(FEATURE_COLUMNS), false)?
+            .finish();
+        Ok(())
+    }
+}
+
+
+impl Verifiable for GeometryTable {
+    fn run_verifier(v: &mut Verifier, pos: usize) -> VerifyResult {
+        v.visit_table(pos)?
+            .visit_field::<ForwardsUOffset<Vector<u32>>>("ends", field(GEOMETRY_ENDS), false)?
+            .visit_field::<ForwardsUOffset<Vector<f64>>>("xy", field(GEOMETRY_XY), false)?
+            .visit_field::<ForwardsUOffset<Vector<f64>>>("z", field(GEOMETRY_Z), false)?
+            .visit_field::<u8>("type", field(GEOMETRY_TYPE), false)?
+            .visit_field::<ForwardsUOffset<Vector<ForwardsUOffset<GeometryTable>>>>(
+                "parts", field(GEOMETRY_PARTS), false)?
+            .finish();
+        Ok(())
+    }
+}
+
+
+fn field(index: u16) -> u16 {
+    flatbuffers::field_index_to_field_offset(index)
+}
+
+
+// Decode one size-prefixed feature.
+fn parse_feature(data: &[u8], geometry_type: u8, has_z: bool,
+                 header_columns: Option<Vector<ForwardsUOffset<ColumnTable>>>) -> Result<Feature, ReadError> {
+    let feature = flatbuffers::size_prefixed_root::<FeatureTable>(data).map_err(invalid_flatbuffer)?;
+    let geometry = match feature.get::<ForwardsUOffset<GeometryTable>>(FEATURE_GEOMETRY) {
+        Some(geometry) => Some(Geometry::new(parse_geometry(geometry, geometry_type, has_z)?)),
+        None => None,
+    };
+    let properties = match feature.get::<ForwardsUOffset<Vector<u8>>>(FEATURE_PROPERTIES) {
+        Some(properties) => {
+            let columns = feature.columns(FEATURE_COLUMNS).or(header_columns)
+                .ok_or_else(|| invalid("feature has properties but there are no columns"))?;
+            Some(parse_properties(properties.bytes(), columns)?)
+        }
+        None => None,
+    };
+    Ok(Feature { bbox: None, geometry, id: None, properties, foreign_members: None })
+}
+
+
+// A geometry's own type is used if it has one, and otherwise `inherited`,
+// the type in the header or, for the parts of a MultiPolygon, Polygon.
+fn parse_geometry(geometry: Fields, inherited: u8, has_z: bool) -> Result<Value, ReadError> {
+    let geometry_type = match geometry.scalar(GEOMETRY_TYPE, UNKNOWN) {
+        UNKNOWN => inherited,
+        own => own,
+    };
+    let parts = || -> Vec<Fields> {
+        geometry.get::<ForwardsUOffset<Vector<ForwardsUOffset<GeometryTable>>>>(GEOMETRY_PARTS)
+            .map_or_else(Vec::new, |parts| parts.iter().collect())
+    };
+    Ok(match geometry_type {
+        POINT => {
+            let mut positions = positions(geometry, has_z)?;
+            match positions.len() {
+                0 => Value::MultiPoint(vec![]),
+                1 => Value::Point(positions.remove(0)),
+                _ => return Err(invalid("Point has more than one position")),
+            }
+        }
+        MULTIPOINT => Value::MultiPoint(positions(geometry, has_z)?),
+        LINESTRING => Value::LineString(positions(geometry, has_z)?),
+        MULTILINESTRING => Value::MultiLineString(split_parts(geometry, has_z)?),
+        POLYGON => Value::Polygon(split_parts(geometry, has_z)?),
+        MULTIPOLYGON => {
+            let polygons = parts().into_iter()
+                .map(|part| match parse_geometry(part, POLYGON, has_z)? {
+                    Value::Polygon(rings) => Ok(rings),
+                    _ => Err(invalid("expected a Polygon in a MultiPolygon")),
+                })
+                .collect::<Result<Vec<_>, ReadError>>()?;
+            Value::MultiPolygon(polygons)
+        }
+        GEOMETRY_COLLECTION => {
+            let geometries = parts().into_iter()
+                .map(|part| parse_geometry(part, UNKNOWN, has_z).map(Geometry::new))
+                .collect::<Result<Vec<_>, ReadError>>()?;
+            Value::GeometryCollection(geometries)
+        }
+        UNKNOWN => return Err(invalid("geometry has no type")),
+        _ => return Err(invalid(&format!("unsupported geometry type {}", geometry_type))),
+    })
+}
+
+
+// The positions of a geometry's flat coordinate arrays. z values are kept
+// unless they are NaN, which is how a missing z is written.
+fn positions(geometry: Fields, has_z: bool) -> Result<Vec<Position>, ReadError> {
+    let xy = match geometry.get::<ForwardsUOffset<Vector<f64>>>(GEOMETRY_XY) {
+        Some(xy) => xy,
+        None => return Ok(Vec::new()),
+    };
+    if !xy.len().is_multiple_of(2) {
+        return Err(invalid("odd number of xy coordinates"));
+    }
+    let z = if has_z { geometry.get::<ForwardsUOffset<Vector<f64>>>(GEOMETRY_Z) } else { None };
+    if z.is_some_and(|z| z.len() != xy.len() / 2) {
+        return Err(invalid("the number of z coordinates differs from the number of positions"));
+    }
+    Ok((0..xy.len() / 2)
+        .map(|i| {
+            let mut position = vec![xy.get(2 * i), xy.get(2 * i + 1)];
+            if let Some(z) = z.map(|z| z.get(i)).filter(|z| !z.is_nan()) {
+                position.push(z);
+            }
+            position
+        })
+        .collect())
+}
+
+
+// The rings or lines of a geometry, split at its "ends". A geometry without
+// ends has a single part, unless it has no positions at all.
+fn split_parts(geometry: Fields, has_z: bool) -> Result<Vec<Vec<Position>>, ReadError> {
+    let positions = positions(geometry, has_z)?;
+    let ends: Vec<usize> = match geometry.get::<ForwardsUOffset<Vector<u32>>>(GEOMETRY_ENDS) {
+        Some(ends) => ends.iter().map(|end| end as usize).collect(),
+        None if positions.is_empty() => Vec::new(),
+        None => vec![positions.len()],
+    };
+    if ends.windows(2).any(|pair| pair[0] > pair[1]) || ends.last().is_some_and(|&end| end != positions.len()) {
+        return Err(invalid("part ends are out of order or don't match the positions"));
+    }
+    let mut positions = positions.into_iter();
+    let mut start = 0;
+    let mut parts = Vec::with_capacity(ends.len());
+    for end in ends {
+        parts.push(positions.by_ref().take(end - start).collect());
+        start = end;
+    }
+    Ok(parts)
+}
+
+
+// Decode a feature's properties: each is the index of its column, as a
+// little-endian u16, followed by the value.
+fn parse_properties(data: &[u8], columns: Vector<ForwardsUOffset<ColumnTable>>)
+    -> Result<Map<String, JsonValue>, ReadError> {
+    let mut properties = Map::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let index = u16::from_le_bytes(bytes(data, pos, 2)?.try_into().unwrap()) as usize;
+        pos += 2;
+        if index >= columns.len() {
+            return Err(invalid(&format!("property for column {} of {}", index, columns.len())));
+        }
+        let column = columns.get(index);
+        let name = column.get::<ForwardsUOffset<&str>>(COLUMN_NAME).unwrap_or_default();
+        let column_type = column.scalar(COLUMN_TYPE, BYTE);
+        let mut fixed = |len: usize| -> Result<&[u8], ReadError> {
+            let value = bytes(data, pos, len)?;
+            pos += len;
+            Ok(value)
+        };
+        let value = match column_type {
+            BYTE => json!(fixed(1)?[0] as i8),
+            UBYTE => json!(fixed(1)?[0]),
+            BOOL => json!(fixed(1)?[0] != 0),
+            SHORT => json!(i16::from_le_bytes(fixed(2)?.try_into().unwrap())),
+            USHORT => json!(u16::from_le_bytes(fixed(2)?.try_into().unwrap())),
+            INT => json!(i32::from_le_bytes(fixed(4)?.try_into().unwrap())),
+            UINT => json!(u32::from_le_bytes(fixed(4)?.try_into().unwrap())),
+            LONG => json!(i64::from_le_bytes(fixed(8)?.try_into().unwrap())),
+            ULONG => json!(u64::from_le_bytes(fixed(8)?.try_into().unwrap())),
+            FLOAT => json!(f32::from_le_bytes(fixed(4)?.try_into().unwrap())),
+            DOUBLE => json!(f64::from_le_bytes(fixed(8)?.try_into().unwrap())),
+            STRING | JSON | DATETIME | BINARY => {
+                let len = le_u32(fixed(4)?, 0)?;
+                let value = fixed(len)?;
+                match column_type {
+                    BINARY => JsonValue::String(value.iter().map(|b| format!("{:02x}", b)).collect()),
+                    _ => {
+                        let text = str::from_utf8(value).map_err(|_| invalid("string value is not UTF-8"))?;
+                        match column_type {
+                            JSON => serde_json::from_str(text)
+                                .map_err(|e| invalid(&format!("column {} is not valid JSON: {}", name, e)))?,
+                            _ => JsonValue::String(text.to_string()),
+                        }
+                    }
+                }
+            }
+            _ => return Err(invalid(&format!("unknown column type {}", column_type))),
+        };
+        properties.insert(name.to_string(), value);
+    }
+    Ok(properties)
+}
+
+
+// A column of the written properties.
+struct Column {
+    name: String,
+    column_type: u8,
+}
+
+
+// One column per property name, in order of first appearance, with a type
+// that fits every non-null value: Bool, Long for integers, Double for other
+// numbers and String, falling back to Double for a mix of integers and
+// other numbers and to Json for anything else.
+fn infer_columns(features: &[Feature]) -> Vec<Column> {
+    let mut columns: Vec<Column> = Vec::new();
+    let mut indexes = HashMap::new();
+    for properties in features.iter().filter_map(|f| f.properties.as_ref()) {
+        for (name, value) in properties {
+            let index = *indexes.entry(name.clone()).or_insert_with(|| {
+                columns.push(Column { name: name.clone(), column_type: UNKNOWN_COLUMN });
+                columns.len() - 1
+            });
+            let column = &mut columns[index];
+            column.column_type = match (column.column_type, json_type(value)) {
+                (a, None) => a,
+                (UNKNOWN_COLUMN, Some(b)) => b,
+                (a, Some(b)) if a == b => a,
+                (LONG, Some(DOUBLE)) | (DOUBLE, Some(LONG)) => DOUBLE,
+                _ => JSON,
+            };
+        }
+    }
+    // A property that is always null still needs a type.
+    for column in &mut columns {
+        if column.column_type == UNKNOWN_COLUMN {
+            column.column_type = STRING;
+        }
+    }
+    columns
+}
+
+
+// Not a column type: that of a column with no values yet.
+const UNKNOWN_COLUMN: u8 = u8::MAX;
+
+
+// The column type of a JSON value, or None for null.
+fn json_type(value: &JsonValue) -> Option<u8> {
+    match *value {
+        JsonValue::Null => None,
+        JsonValue::Bool(_) => Some(BOOL),
+        JsonValue::Number(ref n) if n.is_i64() => Some(LONG),
+        JsonValue::Number(_) => Some(DOUBLE),
+        JsonValue::String(_) => Some(STRING),
+        JsonValue::Array(_) | JsonValue::Object(_) => Some(JSON),
+    }
+}
+
+
+fn value_type(value: &Value) -> u8 {
+    match *value {
+        Value::Point(_) => POINT,
+        Value::MultiPoint(_) => MULTIPOINT,
+        Value::LineString(_) => LINESTRING,
+        Value::MultiLineString(_) => MULTILINESTRING,
+        Value::Polygon(_) => POLYGON,
+        Value::MultiPolygon(_) => MULTIPOLYGON,
+        Value::GeometryCollection(_) => GEOMETRY_COLLECTION,
+    }
+}
+
+
+// The size-prefixed header.
+fn encode_header(extent: &Bbox, geometry_type: u8, has_z: bool, columns: &[Column], count: usize) -> Vec<u8> {
+    let mut fbb = FlatBufferBuilder::new();
+    let envelope = if extent.is_empty() { None }
+                   else { Some(fbb.create_vector(&[extent.xmin(), extent.ymin(), extent.xmax(), extent.ymax()])) };
+    let columns: Vec<_> = columns.iter()
+        .map(|column| {
+            let name = fbb.create_string(&column.name);
+            let start = fbb.start_table();
+            fbb.push_slot_always(field(COLUMN_NAME), name);
+            fbb.push_slot(field(COLUMN_TYPE), column.column_type, BYTE);
+            fbb.end_table(start)
+        })
+        .collect();
+    let columns = if columns.is_empty() { None } else { Some(fbb.create_vector(&columns)) };
+    let start = fbb.start_table();
+    fbb.push_slot(field(CRS_CODE), 4326, 0);
+    let crs = fbb.end_table(start);
+
+    let start = fbb.start_table();
+    if let Some(envelope) = envelope {
+        fbb.push_slot_always(field(HEADER_ENVELOPE), envelope);
+    }
+    fbb.push_slot(field(HEADER_GEOMETRY_TYPE), geometry_type, UNKNOWN);
+    fbb.push_slot(field(HEADER_HAS_Z), has_z, false);
+    if let Some(columns) = columns {
+        fbb.push_slot_always(field(HEADER_COLUMNS), columns);
+    }
+    fbb.push_slot(field(HEADER_FEATURES_COUNT), count as u64, 0);
+    // Without features there is no index.
+    let node_size = if count > 0 { DEFAULT_NODE_SIZE } else { 0 };
+    fbb.push_slot(field(HEADER_INDEX_NODE_SIZE), node_size, DEFAULT_NODE_SIZE);
+    fbb.push_slot_always(field(HEADER_CRS), crs);
+    let header = fbb.end_table(start);
+    fbb.finish_size_prefixed(header, None);
+    fbb.finished_data().to_vec()
+}
+
+
+// One size-prefixed feature.
+fn encode_feature(feature: &Feature, columns: &[Column], has_z: bool) -> Result<Vec<u8>, BboxError> {
+    let mut fbb = FlatBufferBuilder::new();
+    let geometry = match feature.geometry {
+        Some(ref g) => Some(encode_geometry(&mut fbb, &g.value, has_z).map_err(|e| e.in_field("geometry"))?),
+        None => None,
+    };
+    let properties = feature.properties.as_ref()
+        .map(|properties| encode_properties(properties, columns))
+        .filter(|properties| !properties.is_empty())
+        .map(|properties| fbb.create_vector(&properties));
+    let start = fbb.start_table();
+    if let Some(geometry) = geometry {
+        fbb.push_slot_always(field(FEATURE_GEOMETRY), geometry);
+    }
+    if let Some(properties) = properties {
+        fbb.push_slot_always(field(FEATURE_PROPERTIES), properties);
+    }
+    let root = fbb.end_table(start);
+    fbb.finish_size_prefixed(root, None);
+    Ok(fbb.finished_data().to_vec())
+}
+
+
+// A geometry table. Multi-part lines and polygons are written as flat
+// coordinates split at their "ends", and the polygons of a MultiPolygon and
+// members of a GeometryCollection as "parts". Every geometry has its type,
+// so that mixed collections can be read back. Every position must have an x
+// and a y.
+fn encode_geometry(fbb: &mut FlatBufferBuilder, value: &Value, has_z: bool)
+    -> Result<WIPOffset<TableFinishedWIPOffset>, BboxError> {
+    let geometry_type = value_type(value);
+    let coordinates = |e: BboxError| e.in_field("coordinates");
+    Ok(match *value {
+        Value::Point(ref position) => {
+            check_position(position).map_err(coordinates)?;
+            encode_flat(fbb, geometry_type, &[std::slice::from_ref(position)], has_z)
+        }
+        Value::MultiPoint(ref positions) | Value::LineString(ref positions) => {
+            check_line(positions).map_err(coordinates)?;
+            encode_flat(fbb, geometry_type, &[positions], has_z)
+        }
+        Value::MultiLineString(ref lines) | Value::Polygon(ref lines) => {
+            check_lines(lines).map_err(coordinates)?;
+            encode_flat(fbb, geometry_type, lines, has_z)
+        }
+        Value::MultiPolygon(ref polygons) => {
+            let mut parts = Vec::with_capacity(polygons.len());
+            for (i, rings) in polygons.iter().enumerate() {
+                check_lines(rings).map_err(|e| coordinates(e.at_index(i)))?;
+                parts.push(encode_flat(fbb, POLYGON, rings, has_z));
+            }
+            encode_parts(fbb, geometry_type, &parts)
+        }
+        Value::GeometryCollection(ref geometries) => {
+            let mut parts = Vec::with_capacity(geometries.len());
+            for (i, g) in geometries.iter().enumerate() {
+                parts.push(encode_geometry(fbb, &g.value, has_z).map_err(|e| e.at_index(i).in_field("geometries"))?);
+            }
+            encode_parts(fbb, geometry_type, &parts)
+        }
+    })
+}
+
+
+fn check_position(position: &Position) -> Result<(), BboxError> {
+    if position.len() < 2 {
+        return Err(BboxError::new(BboxErrorKind::ShortPosition(position.len())));
+    }
+    Ok(())
+}
+
+
+fn check_line(line: &[Position]) -> Result<(), BboxError> {
+    for (i, position) in line.iter().enumerate() {
+        check_position(position).map_err(|e| e.at_index(i))?;
+    }
+    Ok(())
+}
+
+
+fn check_lines<L: AsRef<[Position]>>(lines: &[L]) -> Result<(), BboxError> {
+    for (i, line) in lines.iter().enumerate() {
+        check_line(line.as_ref()).map_err(|e| e.at_index(i))?;
+    }
+    Ok(())
+}
+
+
+// A geometry of the given lines, or rings, with z values if the file has
+// them. A missing z is written as NaN. The positions have been checked.
+fn encode_flat<L: AsRef<[Position]>>(fbb: &mut FlatBufferBuilder, geometry_type: u8, lines: &[L], has_z: bool)
+    -> WIPOffset<TableFinishedWIPOffset> {
+    let positions = lines.iter().flat_map(|line| line.as_ref().iter());
+    let xy: Vec<f64> = positions.clone().flat_map(|p| p[..2].iter().cloned()).collect();
+    let z: Vec<f64> = positions.map(|p| p.get(2).cloned().unwrap_or(f64::NAN)).collect();
+    let ends: Vec<u32> = lines.iter()
+        .scan(0, |end, line| {
+            *end += line.as_ref().len() as u32;
+            Some(*end)
+        })
+        .collect();
+
+    let ends = if ends.len() > 1 { Some(fbb.create_vector(&ends)) } else { None };
+    let xy = if xy.is_empty() { None } else { Some(fbb.create_vector(&xy)) };
+    let z = if has_z && !z.is_empty() { Some(fbb.create_vector(&z)) } else { None };
+    let start = fbb.start_table();
+    if let Some(ends) = ends {
+        fbb.push_slot_always(field(GEOMETRY_ENDS), ends);
+    }
+    if let Some(xy) = xy {
+        fbb.push_slot_always(field(GEOMETRY_XY), xy);
+    }
+    if let Some(z) = z {
+        fbb.push_slot_always(field(GEOMETRY_Z), z);
+    }
+    fbb.push_slot(field(GEOMETRY_TYPE), geometry_type, UNKNOWN);
+    fbb.end_table(start)
+}
+
+
+fn encode_parts(fbb: &mut FlatBufferBuilder, geometry_type: u8, parts: &[WIPOffset<TableFinishedWIPOffset>])
+    -> WIPOffset<TableFinishedWIPOffset> {
+    let parts = if parts.is_empty() { None } else { Some(fbb.create_vector(parts)) };
+    let start = fbb.start_table();
+    fbb.push_slot(field(GEOMETRY_TYPE), geometry_type, UNKNOWN);
+    if let Some(parts) = parts {
+        fbb.push_slot_always(field(GEOMETRY_PARTS), parts);
+    }
+    fbb.end_table(start)
+}
+
+
+// Encode the non-null properties that have a column, in column order.
+fn encode_properties(properties: &Map<String, JsonValue>, columns: &[Column]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (index, column) in columns.iter().enumerate() {
+        let value = match properties.get(&column.name) {
+            None | Some(&JsonValue::Null) => continue,
+            Some(value) => value,
+        };
+        out.extend_from_slice(&(index as u16).to_le_bytes());
+        match column.column_type {
+            BOOL => out.push(value.as_bool().unwrap() as u8),
+            LONG => out.extend_from_slice(&value.as_i64().unwrap().to_le_bytes()),
+            DOUBLE => out.extend_from_slice(&value.as_f64().unwrap().to_le_bytes()),
+            _ => {
+                let text = match *value {
+                    JsonValue::String(ref s) if column.column_type == STRING => s.clone(),
+                    _ => value.to_string(),
+                };
+                out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                out.extend_from_slice(text.as_bytes());
+            }
+        }
+    }
+    out
+}
+
+
+// A node of the packed R-tree: a bbox, and the byte offset of its feature
+// for a leaf or the index of its first child otherwise.
+#[derive(Debug, Clone, Copy)]
+struct NodeItem {
+    xmin: f64,
+    ymin: f64,
+    xmax: f64,
+    ymax: f64,
+    offset: u64,
+}
+
+
+impl NodeItem {
+    // The node of a feature without coordinates, written as the reference
+    // implementations do: minimums of +infinity and maximums of -infinity,
+    // which no query intersects and which don't widen a parent.
+    fn empty(offset: u64) -> Self {
+        NodeItem { xmin: f64::INFINITY, ymin: f64::INFINITY, xmax: f64::NEG_INFINITY, ymax: f64::NEG_INFINITY,
+                   offset }
+    }
+
+    fn new(bbox: &Bbox, offset: u64) -> Self {
+        if bbox.is_empty() {
+            return NodeItem::empty(offset);
+        }
+        NodeItem { xmin: bbox.xmin(), ymin: bbox.ymin(), xmax: bbox.xmax(), ymax: bbox.ymax(), offset }
+    }
+
+    fn read(data: &[u8]) -> Self {
+        let f = |i: usize| f64::from_le_bytes(data[8 * i..8 * i + 8].try_into().unwrap());
+        NodeItem { xmin: f(0), ymin: f(1), xmax: f(2), ymax: f(3),
+                   offset: u64::from_le_bytes(data[32..40].try_into().unwrap()) }
+    }
+
+    fn write<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        for v in &[self.xmin, self.ymin, self.xmax, self.ymax] {
+            out.write_all(&v.to_le_bytes())?;
+        }
+        out.write_all(&self.offset.to_le_bytes())
+    }
+
+    fn expand(&mut self, other: &NodeItem) {
+        self.xmin = self.xmin.min(other.xmin);
+        self.ymin = self.ymin.min(other.ymin);
+        self.xmax = self.xmax.max(other.xmax);
+        self.ymax = self.ymax.max(other.ymax);
+    }
+
+    // The bbox as a "bbox" member, unless it is empty.
+    fn bbox(self) -> Option<Vec<f64>> {
+        if self.xmin > self.xmax || self.ymin > self.ymax {
+            None
+        } else {
+            Some(vec![self.xmin, self.ymin, self.xmax, self.ymax])
+        }
+    }
+}
+
+
+// The number of nodes on each level of a packed R-tree over `count` items,
+// from the leaves up to the root. Even a single leaf has a root above it.
+fn level_sizes(count: usize, node_size: usize) -> Vec<usize> {
+    let mut sizes = vec![count];
+    let mut n = count;
+    loop {
+        n = n.div_ceil(node_size);
+        sizes.push(n);
+        if n == 1 {
+            return sizes;
+        }
+    }
+}
+
+
+// Build the packed R-tree over the leaves, which are in Hilbert order. The
+// nodes are stored root first and leaves last, and each parent covers
+// `node_size` consecutive nodes of the level below.
+fn build_index(leaves: Vec<NodeItem>, node_size: usize) -> Vec<NodeItem> {
+    let sizes = level_sizes(leaves.len(), node_size);
+    let total: usize = sizes.iter().sum();
+    // The start of each level, from the leaves up.
+    let starts: Vec<usize> = sizes.iter()
+        .scan(total, |end, size| {
+            *end -= size;
+            Some(*end)
+        })
+        .collect();
+
+    let empty = NodeItem::empty(0);
+    let mut nodes = vec![empty; total];
+    nodes[starts[0]..].copy_from_slice(&leaves);
+    for level in 0..sizes.len() - 1 {
+        let (children, parents) = (starts[level], starts[level + 1]);
+        for i in 0..sizes[level + 1] {
+            let first = children + i * node_size;
+            let last = (first + node_size).min(children + sizes[level]);
+            let mut parent = NodeItem { offset: first as u64, ..empty };
+            for child in &nodes[first..last] {
+                parent.expand(child);
+            }
+            nodes[parents + i] = parent;
+        }
+    }
+    nodes
+}
+
+
+// The position along the Hilbert curve of the centre of a bbox, on a grid
+// over `extent`. Empty bboxes get 0, so they go last in the descending order
+// write_flatgeobuf sorts by.
+fn hilbert_value(bbox: &Bbox, extent: &Bbox) -> u32 {
+    if bbox.is_empty() {
+        return 0;
+    }
+    let grid = |centre: f64, min: f64, max: f64| {
+        if max > min { (HILBERT_MAX * (centre - min) / (max - min)).floor() as u32 } else { 0 }
+    };
+    let x = grid((bbox.xmin() + bbox.xmax()) / 2.0, extent.xmin(), extent.xmax());
+    let y = grid((bbox.ymin() + bbox.ymax()) / 2.0, extent.ymin(), extent.ymax());
+    hilbert(x, y)
+}
+
+
+// The Hilbert index of a point on a 2^16 x 2^16 grid, from "Fast Hilbert
+// curve generation, sorting, and range queries" by rawrunprotected, as used
+// by FlatGeobuf.
+fn hilbert(x: u32, y: u32) -> u32 {
+    let mut a = x ^ y;
+    let mut b = 0xFFFF ^ a;
+    let mut c = 0xFFFF ^ (x | y);
+    let mut d = x & (y ^ 0xFFFF);
+
+    let mut aa = a | (b >> 1);
+    let mut bb = (a >> 1) ^ a;
+    let mut cc = ((c >> 1) ^ (b & (d >> 1))) ^ c;
+    let mut dd = ((a & (c >> 1)) ^ (d >> 1)) ^ d;
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    aa = (a & (a >> 2)) ^ (b & (b >> 2));
+    bb = (a & (b >> 2)) ^ (b & ((a ^ b) >> 2));
+    cc ^= (a & (c >> 2)) ^ (b & (d >> 2));
+    dd ^= (b & (c >> 2)) ^ ((a ^ b) & (d >> 2));
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    aa = (a & (a >> 4)) ^ (b & (b >> 4));
+    bb = (a & (b >> 4)) ^ (b & ((a ^ b) >> 4));
+    cc ^= (a & (c >> 4)) ^ (b & (d >> 4));
+    dd ^= (b & (c >> 4)) ^ ((a ^ b) & (d >> 4));
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    cc ^= (a & (c >> 8)) ^ (b & (d >> 8));
+    dd ^= (b & (c >> 8)) ^ ((a ^ b) & (d >> 8));
+
+    a = cc ^ (cc >> 1);
+    b = dd ^ (dd >> 1);
+
+    let mut i0 = x ^ y;
+    let mut i1 = b | (0xFFFF ^ (i0 | a));
+    i0 = interleave(i0);
+    i1 = interleave(i1);
+    (i1 << 1) | i0
+}
+
+
+// Spread the low 16 bits of `n` out to the even bits.
+fn interleave(mut n: u32) -> u32 {
+    n = (n | (n << 8)) & 0x00FF_00FF;
+    n = (n | (n << 4)) & 0x0F0F_0F0F;
+    n = (n | (n << 2)) & 0x3333_3333;
+    (n | (n << 1)) & 0x5555_5555
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use glob;
+
+
+/// A format of input that can be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// A single GeoJSON document.
+    GeoJson,
+    /// One GeoJSON text per line, or per record separator (RFC 8142). The
+    /// framing is detected from the input.
+    Sequence,
+    /// An ESRI Shapefile, with its .shx and .dbf alongside.
+    Shapefile,
+    FlatGeobuf,
+    /// One geometry per line, as WKT or hex WKB.
+    Wkt,
+    /// One point per row, in a longitude and a latitude column.
+    Csv,
+}
+
+
+impl InputFormat {
+    /// The format with this name or file extension, e.g. "ndjson" or "shp",
+    /// in any case.
+    pub fn from_name(name: &str) -> Option<InputFormat> {
+        match &*name.to_lowercase() {
+            "geojson" | "json" => Some(InputFormat::GeoJson),
+            "ndjson" | "jsonl" | "geojsonl" | "geojsonseq" | "geojsons" => Some(InputFormat::Sequence),
+            "shapefile" | "shp" => Some(InputFormat::Shapefile),
+            "flatgeobuf" | "fgb" => Some(InputFormat::FlatGeobuf),
+            "wkt" | "wkb" => Some(InputFormat::Wkt),
+            "csv" => Some(InputFormat::Csv),
+            _ => None,
+        }
+    }
+
+    /// Whether the format declares the extent of its contents in a header.
+    pub fn has_header(self) -> bool {
+        matches!(self, InputFormat::Shapefile | InputFormat::FlatGeobuf)
+    }
+
+    /// Guess the format from a file's extension, in any case, looking
+    /// through a .gz or .zst suffix.
+    pub fn from_extension(filename: &str) -> Option<InputFormat> {
+        let mut path = Path::new(filename);
+        let compressed = path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ["gz", "zst", "zstd"].contains(&&*ext.to_lowercase()));
+        if compressed {
+            path = Path::new(path.file_stem()?);
+        }
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(InputFormat::from_name)
+    }
+}
+
+
+/// Expand an input argument into the files it names: "-" for stdin, a file,
+/// the files under a directory or the files matching a glob pattern.
+///
+/// Directories are searched recursively for files with a known extension,
+/// other than CSV files, which are as likely to hold anything else as
+/// points, and the files found are sorted. Arguments that aren't an existing
+/// path are tried as glob patterns, and if nothing matches the argument is
+/// returned as it is, so that opening it reports that it doesn't exist. The
+/// only error is a directory that can't be read.
+pub fn expand_input(arg: &str) -> io::Result<Vec<String>> {
+    let path = Path::new(arg);
+    if arg == "-" || path.is_file() {
+        return Ok(vec![arg.to_string()]);
+    }
+    if path.is_dir() {
+        let mut files = Vec::new();
+        find_files(path, &mut files)?;
+        files.sort();
+        return Ok(files);
+    }
+
+    let files: Vec<String> = match glob::glob(arg) {
+        Ok(paths) => paths.filter_map(Result::ok)
+                          .filter(|p| p.is_file())
+                          .map(|p| p.to_string_lossy().into_owned())
+                          .collect(),
+        Err(_) => Vec::new(),
+    };
+    if files.is_empty() {
+        return Ok(vec![arg.to_string()]);
+    }
+    Ok(files)
+}
+
+
+fn find_files(dir: &Path, files: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_files(&path, files)?;
+        } else if path.to_str().and_then(InputFormat::from_extension).is_some_and(|f| f != InputFormat::Csv) {
+            files.push(path.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
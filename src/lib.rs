@@ -0,0 +1,97 @@
+//! par_bbox computes the bounding box of GeoJSON objects in parallel with a
+//! divide-and-conquer approach using rayon. See the README for an overview of
+//! how the work is split up.
+//!
+//! The main entry points are the `ToBbox` trait, implemented for every
+//! `geojson` type, and the generic `compute_bbox` helper that the trait
+//! implementations are built on. Malformed input is reported as a
+//! `BboxError` carrying the path of the offending element. Features with no
+//! coordinates have an empty bbox and are skipped; `Skipped` counts them.
+//!
+//! `feature_bboxes` computes the bbox of every feature of a
+//! FeatureCollection in parallel, for output as CSV or NDJSON rows, and
+//! `fill_bbox_members` writes the results back as RFC 7946 "bbox" members.
+//! `check_bbox_members` compares existing members with the computed extents.
+//!
+//! Parallel work runs on rayon's global pool unless `ToBbox::to_bbox_in` is
+//! given a `ThreadPool`.
+//!
+//! `stream_bbox` computes the same total without holding the whole document
+//! in memory. `sequence_bbox` does the same for newline-delimited GeoJSON
+//! and RFC 8142 GeoJSON text sequences. `scan_bbox` skips building the
+//! `geojson` types altogether and computes the bbox while scanning the JSON,
+//! and `par_scan_bbox` scans the features of a FeatureCollection in parallel.
+//!
+//! `decompress` wraps a reader of gzip or zstd input, recognized by its
+//! magic bytes, so that `stream_bbox` and `sequence_bbox` can read compressed
+//! files.
+//!
+//! `expand_input` turns a file, directory or glob pattern argument into the
+//! files it names, and `InputFormat::from_extension` guesses their formats.
+//!
+//! `wkt_bbox` computes the total bbox of WKT or hex WKB geometries, one per
+//! line, parsing them with `parse_wkt` and `parse_wkb`. `csv_bbox` does the
+//! same for points in the longitude and latitude columns of a CSV file.
+//!
+//! `read_shapefile` reads an ESRI Shapefile into a FeatureCollection, keeping
+//! the extents declared in the file as "bbox" members. `read_flatgeobuf`
+//! does the same for FlatGeobuf, whose spatial index `write_flatgeobuf`
+//! builds from feature bboxes computed in parallel.
+//!
+//! `write_bbox` writes a bbox as JSON, WKT, a GeoJSON Feature, CSV or GDAL
+//! `-te` arguments, and `PhaseStats` summarizes benchmark timings.
+
+extern crate csv;
+extern crate flatbuffers;
+extern crate flate2;
+extern crate geojson;
+extern crate glob;
+extern crate rayon;
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate zstd;
+
+mod bbox;
+mod check;
+mod compression;
+mod csv_points;
+mod error;
+mod features;
+mod flatgeobuf;
+mod input;
+mod members;
+mod output;
+mod scan;
+mod sequence;
+mod shapefile;
+mod skipped;
+mod split;
+mod stats;
+mod stream;
+mod to_bbox;
+mod wkb;
+mod wkt;
+
+pub use rayon::ThreadPool;
+
+pub use bbox::Bbox;
+pub use check::{CheckReport, Mismatch, bbox_difference, check_bbox_members};
+pub use compression::{Compression, decompress};
+pub use csv_points::{CsvOptions, CsvPoints, csv_bbox};
+pub use error::{BboxError, BboxErrorKind, JsonPath, ReadError};
+pub use features::{FeatureBbox, FeatureKey, feature_bboxes, write_csv, write_ndjson};
+pub use flatgeobuf::{DEFAULT_NODE_SIZE, parse_flatgeobuf, read_flatgeobuf, write_flatgeobuf};
+pub use input::{InputFormat, expand_input};
+pub use members::{MemberOptions, fill_bbox_members};
+pub use output::{BboxFormat, write_bbox};
+pub use scan::{par_scan_bbox, scan_bbox};
+pub use sequence::{DEFAULT_CHUNK_SIZE, RECORD_SEPARATOR, sequence_bbox};
+pub use shapefile::{parse_shapefile, read_shapefile};
+pub use skipped::{SkipReason, Skipped};
+pub use stats::PhaseStats;
+pub use stream::{DEFAULT_BATCH_SIZE, Streamed, stream_bbox};
+pub use to_bbox::{BboxOptions, GrainSize, MIN_GRAIN_SIZE, SplitBy, Strategy, ToBbox, thread_pool, compute_bbox, compute_bbox_weighted,
+                  compute_bbox_with};
+pub use wkb::{parse_hex_wkb, parse_wkb};
+pub use wkt::{parse_wkt, wkt_bbox};
@@ -1,193 +1,971 @@
 extern crate geojson;
+extern crate memmap2;
+extern crate par_bbox;
 extern crate rayon;
+#[macro_use]
+extern crate serde_json;
 extern crate time;
 
-use std::error::Error;
 use std::env;
 use std::fs::File;
-use std::io::{Read};
-
-use geojson::{GeoJson, Feature, FeatureCollection, Geometry, Position, Value};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::ops::Deref;
+use std::path::Path;
+use std::str;
+
+use geojson::{Feature, FeatureCollection, GeoJson};
+use memmap2::Mmap;
+use par_bbox::{Bbox, BboxFormat, BboxOptions, CsvOptions, CsvPoints, FeatureKey, InputFormat, MemberOptions, PhaseStats,
+               ReadError, Skipped, Streamed, ToBbox};
+use rayon::prelude::*;
 use time::PreciseTime;
 
 
-#[derive(Debug)]
-struct Bbox {
-    xmin: f64,
-    xmax: f64,
-    ymin: f64,
-    ymax: f64,
+const USAGE: &str = "\
+Usage: $par_bbox [options] INPUT...
+
+Each INPUT is a file, a directory (searched recursively for GeoJSON,
+Shapefile and FlatGeobuf files), a glob pattern, or - for stdin. With
+several inputs, the files are read in parallel and the bbox of each is
+printed followed by their union. Gzip and zstd input is recognized by its
+magic bytes and decompressed.
+
+Options:
+    --antimeridian          Report the smallest longitude interval, which may
+                            cross the antimeridian (xmin > xmax)
+    --per-feature FORMAT    Write the bbox of every feature instead of the
+                            total, as csv or ndjson
+    --key PROPERTY          Identify features by this property instead of
+                            their id in --per-feature output
+    --write-bbox            Write the input back out with \"bbox\" members
+                            on the FeatureCollection and every Feature
+    --geometry-bbox         Like --write-bbox, and also on every Geometry
+    --to-fgb                Convert the input to FlatGeobuf, with a packed
+                            Hilbert R-tree index of the feature bboxes
+    -o, --output PATH       Where --write-bbox and --to-fgb write to
+                            (default: stdout)
+    --check                 Compare existing \"bbox\" members with the
+                            computed extents and report every mismatch
+    --tolerance T           Exit non-zero from --check only if a mismatch is
+                            larger than T, and only warn about a Shapefile
+                            or FlatGeobuf header bbox that differs by more
+                            (default: 0)
+    --stream                Compute the total bbox while reading, without
+                            holding the whole file in memory
+    --batch-size N          Features per batch with --stream (default: 4096)
+    --scan                  Compute the total bbox while scanning the JSON,
+                            without building GeoJSON objects, and scan the
+                            features of a FeatureCollection in parallel
+    --input FORMAT          The input format: geojson, ndjson/geojsonseq for
+                            newline-delimited GeoJSON or RFC 8142 text
+                            sequences, shapefile, flatgeobuf, wkt/wkb for
+                            one WKT or hex WKB geometry per line, or csv for
+                            points in longitude and latitude columns. By
+                            default it is guessed from the file extension,
+                            falling back to geojson (always for stdin)
+    --lon NAME, --lat NAME  The coordinate columns of csv input (default:
+                            detected from headers such as lon, lng,
+                            longitude or x and lat, latitude or y)
+    --csv-delimiter CHAR    The field delimiter of csv input, a single
+                            character or tab (default: ,)
+    --format FORMAT         Print the total bbox as json ([xmin, ymin, xmax,
+                            ymax]), wkt, geojson (a Feature with the bbox
+                            polygon), csv or gdal (-te xmin ymin xmax ymax).
+                            Timings and other diagnostics go to stderr
+    --strategy NAME         How the bbox of a parsed file is computed: join
+                            (recursive rayon::join, the default), reduce
+                            (rayon par_iter reduce) or sequential
+    --threads N             Number of threads to use (default: one per CPU)
+    --bench N               Read, parse and compute the bbox N times and
+                            print the min, median, p95 and max time of each
+                            phase as JSON. The input must be a file
+    --bench-threads LIST    Comma-separated thread counts for --bench to
+                            sweep, e.g. 1,2,4,8 (default: one per CPU)";
+
+
+#[derive(Clone, Copy, PartialEq)]
+enum RowFormat {
+    Csv,
+    Ndjson,
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    // Print the total bbox.
+    Total,
+    // Print the bbox of every feature as rows.
+    PerFeature(RowFormat),
+    // Write the input back out with bbox members filled in.
+    WriteBbox { geometries: bool },
+    // Compare existing bbox members with the computed extents.
+    Check,
+    // Convert the input to FlatGeobuf.
+    ToFlatGeobuf,
+    // Time every phase over repeated runs.
+    Bench { repeats: usize },
+}
+
+
+struct Options {
+    // Files to read, with "-" for stdin.
+    filenames: Vec<String>,
+    antimeridian: bool,
+    mode: Mode,
+    key: FeatureKey,
+    output: Option<String>,
+    tolerance: f64,
+    // Batch size, if streaming.
+    stream: Option<usize>,
+    // Whether to scan the JSON instead of parsing it.
+    scan: bool,
+    // The input format, if not guessed from each file's extension.
+    input: Option<InputFormat>,
+    // How to print the total bbox, if not as text.
+    format: Option<BboxFormat>,
+    csv: CsvOptions,
+    bbox_options: BboxOptions,
+    // Size of rayon's global pool, if not the default.
+    threads: Option<usize>,
+    // Thread counts to benchmark, if not just the default.
+    bench_threads: Vec<usize>,
+}
+
+
+impl Options {
+    fn input_format(&self, filename: &str) -> InputFormat {
+        self.input
+            .or_else(|| InputFormat::from_extension(filename))
+            .unwrap_or(InputFormat::GeoJson)
+    }
+}
+
+
+fn usage_and_exit() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(1);
 }
 
 
-impl Bbox {
-    // Ignore antimeridian crossings for now
-    pub fn merge(&self, other: &Bbox) -> Self {
-        Bbox {
-            xmin: self.xmin.min(other.xmin),
-            xmax: self.xmax.max(other.xmax),
-            ymin: self.ymin.min(other.ymin),
-            ymax: self.ymax.max(other.ymax),
+// Parse the command line. Bail if we're not called correctly.
+fn parse_args() -> Options {
+    let mut filenames = Vec::new();
+    let mut antimeridian = false;
+    let mut mode = Mode::Total;
+    let mut key = FeatureKey::Id;
+    let mut output = None;
+    let mut tolerance = 0.0;
+    let mut stream = None;
+    let mut scan = false;
+    let mut input = None;
+    let mut format = None;
+    let mut csv = CsvOptions::default();
+    let mut bbox_options = BboxOptions::default();
+    let mut threads = None;
+    let mut bench_threads = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--antimeridian" => antimeridian = true,
+            "--per-feature" => {
+                mode = match args.next().as_deref() {
+                    Some("csv") => Mode::PerFeature(RowFormat::Csv),
+                    Some("ndjson") => Mode::PerFeature(RowFormat::Ndjson),
+                    _ => usage_and_exit(),
+                };
+            }
+            "--write-bbox" => mode = Mode::WriteBbox { geometries: false },
+            "--geometry-bbox" => mode = Mode::WriteBbox { geometries: true },
+            "--to-fgb" => mode = Mode::ToFlatGeobuf,
+            "-o" | "--output" => {
+                output = Some(args.next().unwrap_or_else(|| usage_and_exit()));
+            }
+            "--check" => mode = Mode::Check,
+            "--tolerance" => {
+                tolerance = match args.next().map(|t| t.parse()) {
+                    Some(Ok(t)) => t,
+                    _ => usage_and_exit(),
+                };
+            }
+            "--stream" => stream = stream.or(Some(par_bbox::DEFAULT_BATCH_SIZE)),
+            "--scan" => scan = true,
+            "--batch-size" => {
+                stream = match args.next().map(|n| n.parse()) {
+                    Some(Ok(n)) if n > 0 => Some(n),
+                    _ => usage_and_exit(),
+                };
+            }
+            "--input" => {
+                input = match args.next().as_deref().and_then(InputFormat::from_name) {
+                    Some(format) => Some(format),
+                    None => usage_and_exit(),
+                };
+            }
+            "--lon" => csv.lon = Some(args.next().unwrap_or_else(|| usage_and_exit())),
+            "--lat" => csv.lat = Some(args.next().unwrap_or_else(|| usage_and_exit())),
+            "--csv-delimiter" => {
+                csv.delimiter = match args.next().as_deref() {
+                    Some("tab") => b'\t',
+                    Some(c) if c.len() == 1 => c.as_bytes()[0],
+                    _ => usage_and_exit(),
+                };
+            }
+            "--format" => {
+                format = match args.next().as_deref().and_then(BboxFormat::from_name) {
+                    Some(format) => Some(format),
+                    None => usage_and_exit(),
+                };
+            }
+            "--strategy" => {
+                bbox_options.strategy = match args.next().as_deref().and_then(par_bbox::Strategy::from_name) {
+                    Some(strategy) => strategy,
+                    None => usage_and_exit(),
+                };
+            }
+            "--threads" => {
+                threads = match args.next().map(|n| n.parse()) {
+                    Some(Ok(n)) if n > 0 => Some(n),
+                    _ => usage_and_exit(),
+                };
+            }
+            "--bench" => {
+                mode = match args.next().map(|n| n.parse()) {
+                    Some(Ok(repeats)) if repeats > 0 => Mode::Bench { repeats },
+                    _ => usage_and_exit(),
+                };
+            }
+            "--bench-threads" => {
+                let list = args.next().unwrap_or_else(|| usage_and_exit());
+                for n in list.split(',') {
+                    match n.trim().parse() {
+                        Ok(n) if n > 0 => bench_threads.push(n),
+                        _ => usage_and_exit(),
+                    }
+                }
+            }
+            "--key" => {
+                key = FeatureKey::Property(args.next().unwrap_or_else(|| usage_and_exit()));
+            }
+            _ if arg.starts_with("--") => usage_and_exit(),
+            _ => match par_bbox::expand_input(&arg) {
+                Ok(files) => filenames.extend(files),
+                Err(e) => {
+                    eprintln!("Could not read directory '{}': {}", arg, e);
+                    std::process::exit(1);
+                }
+            },
         }
     }
+
+    if filenames.is_empty() {
+        usage_and_exit();
+    }
+    if !bench_threads.is_empty() && !matches!(mode, Mode::Bench { .. }) {
+        eprintln!("--bench-threads needs --bench");
+        usage_and_exit();
+    }
+    // Every repeat reads the input again, and stdin can only be read once.
+    if matches!(mode, Mode::Bench { .. }) && filenames.iter().any(|f| f == "-") {
+        eprintln!("--bench needs a file, not stdin");
+        usage_and_exit();
+    }
+    if format.is_some() && mode != Mode::Total {
+        eprintln!("--format only applies to the total bbox");
+        usage_and_exit();
+    }
+    if filenames.len() > 1 && mode != Mode::Total {
+        eprintln!("Only the total bbox can be computed for several inputs");
+        usage_and_exit();
+    }
+
+    let csv_only = csv != CsvOptions::default();
+    let options = Options { filenames, antimeridian, mode, key, output, tolerance, stream, scan, input,
+                            format, csv, bbox_options, threads, bench_threads };
+    let lines = matches!(options.input_format(&options.filenames[0]),
+                         InputFormat::Sequence | InputFormat::Wkt | InputFormat::Csv);
+    if mode != Mode::Total && (stream.is_some() || scan || lines) {
+        eprintln!("--stream, --scan, sequence, WKT and CSV input only compute the total bbox");
+        usage_and_exit();
+    }
+    if csv_only && !options.filenames.iter().any(|f| options.input_format(f) == InputFormat::Csv) {
+        eprintln!("--lon, --lat and --csv-delimiter only apply to csv input");
+        usage_and_exit();
+    }
+    if stream.is_some() && scan {
+        eprintln!("Choose one of --stream and --scan");
+        usage_and_exit();
+    }
+    let binary = options.filenames.iter().any(|f| options.input_format(f).has_header());
+    if binary && (stream.is_some() || scan) {
+        eprintln!("--stream and --scan only read GeoJSON");
+        usage_and_exit();
+    }
+    options
 }
 
 
-trait ToBbox {
-    fn to_bbox(&self) -> Bbox;
+// Open a file, or stdin for "-", decompressing gzip or zstd input as it is
+// read.
+fn open_input(filename: &str) -> io::Result<Box<dyn Read + Send>> {
+    if filename == "-" {
+        par_bbox::decompress(io::stdin())
+    } else {
+        par_bbox::decompress(File::open(filename)?)
+    }
 }
 
 
-impl ToBbox for Position {
-    // A GeoJson::Position is a (longitude, latitude) tuple. The min/max of
-    // the bounding box are the longitude, latitude of the Position.
-    fn to_bbox(&self) -> Bbox {
-        Bbox { xmin: self[0], ymin: self[1], xmax: self[0], ymax: self[1] }
+// Open the file specified on the command line or bail if we can't.
+fn open_or_fail(filename: &str) -> Box<dyn Read + Send> {
+    match open_input(filename) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Could not open '{}': {}", filename, e);
+            std::process::exit(1);
+        }
     }
 }
 
 
-impl ToBbox for Geometry {
-    fn to_bbox(&self) -> Bbox { self.value.to_bbox() }
+// The contents of an input: a file mapped into memory, or everything read
+// from stdin or a file that can't be mapped.
+enum InputData {
+    Mapped(Mmap),
+    Read(Vec<u8>),
 }
 
 
-impl ToBbox for Feature {
-    // A Feature's bounding box is the bounding box of its geometry. We assume
-    // features will have a geometry, even though it is technically optional.
-    fn to_bbox(&self) -> Bbox { self.geometry.as_ref().unwrap().to_bbox() }
+impl Deref for InputData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self {
+            InputData::Mapped(ref map) => map,
+            InputData::Read(ref data) => data,
+        }
+    }
 }
 
 
-impl ToBbox for FeatureCollection {
-    // Recursively split up the feature collection's bounding box into the
-    // bounding box of the individual features.
-    fn to_bbox(&self) -> Bbox {
-        compute_bbox(&self.features, &|ref f| f.to_bbox())
+// Map a file into memory, or read stdin. Empty files and anything that isn't
+// a regular file, like a named pipe, can't be mapped and are read instead.
+fn load_input(filename: &str) -> io::Result<InputData> {
+    let mut data = Vec::new();
+    if filename == "-" {
+        io::stdin().read_to_end(&mut data)?;
+        return Ok(InputData::Read(data));
+    }
+    let mut file = File::open(filename)?;
+    let metadata = file.metadata()?;
+    if metadata.is_file() && metadata.len() > 0 {
+        // The map is only valid while no other process truncates the file,
+        // as for any tool that maps its input.
+        return unsafe { Mmap::map(&file) }.map(InputData::Mapped);
     }
+    file.read_to_end(&mut data)?;
+    Ok(InputData::Read(data))
 }
 
 
-impl ToBbox for GeoJson {
-    fn to_bbox(&self) -> Bbox {
-        match *self {
-            GeoJson::Geometry(ref geometry) => geometry.to_bbox(),
-            GeoJson::Feature(ref feature) => feature.to_bbox(),
-            GeoJson::FeatureCollection(ref fc) => fc.to_bbox(),
+// An input loaded into memory, with when each phase of loading it ended.
+struct Loaded {
+    data: InputData,
+    start: PreciseTime,
+    end_read: PreciseTime,
+    // When decompressing ended, if the input was compressed.
+    end_decompressed: Option<PreciseTime>,
+}
+
+
+impl Loaded {
+    fn end(&self) -> PreciseTime {
+        self.end_decompressed.unwrap_or(self.end_read)
+    }
+
+    fn print_times(&self) {
+        eprintln!("Time to read: {}", seconds(self.start, self.end_read));
+        if let Some(end) = self.end_decompressed {
+            eprintln!("Time to decompress: {}", seconds(self.end_read, end));
         }
     }
 }
 
 
-// This is a helper function that we use a bunch below in the bounding box
-// calculation of each geometry type.
-fn position_bbox(p: &Position) -> Bbox { p.to_bbox() }
+// Load an input and decompress it into memory if it is gzip or zstd,
+// recognized by its magic bytes.
+fn load(filename: &str) -> io::Result<Loaded> {
+    let start = PreciseTime::now();
+    let data = load_input(filename)?;
+    let end_read = PreciseTime::now();
+    if par_bbox::Compression::detect(&data).is_none() {
+        return Ok(Loaded { data, start, end_read, end_decompressed: None });
+    }
+    let mut decompressed = Vec::new();
+    par_bbox::decompress(&data[..])?.read_to_end(&mut decompressed)?;
+    Ok(Loaded { data: InputData::Read(decompressed), start, end_read, end_decompressed: Some(PreciseTime::now()) })
+}
 
 
-impl ToBbox for Value {
-    fn to_bbox(&self) -> Bbox {
-        match *self {
-            // Point is GeoJson::Position or Vec<f64> which is
-            // a [longitude,latitude] pair
-            Value::Point(ref p) => p.to_bbox(),
-
-            // MultiPoint is Vec<Position>
-            // Break up the MultiPoint into smaller MultiPoints until we get
-            // to a single Position value, then use position_bbox to return
-            // the single position's value and combine back up the chain.
-            Value::MultiPoint(ref vp) => compute_bbox(vp, &position_bbox),
-
-            // LineString is Vec<Position>
-            Value::LineString(ref vp) => compute_bbox(vp, &position_bbox),
-
-            // MultiLineString is Vec<Vec<Position>>
-            Value::MultiLineString(ref vvp) => compute_bbox(vvp, &|ref vp| compute_bbox(vp, &position_bbox)),
-
-            // Polygon is Vec<Vec<Position>>. The first element is the outer
-            // ring / exterior of the polygon which we use to compute the
-            // bounding box of the total polygon.  Extract the first element
-            // (which is like a LineString) and return its bounding box.
-            Value::Polygon(ref vvp) => compute_bbox(&vvp[0], &position_bbox),
-
-            // MultiPolygon is Vec<Vec<Vec<Position>>>, a Vec of polygon
-            // coordinates. When we get to an individual polygon, just use its
-            // outer ring like the Polygon code above.
-            Value::MultiPolygon(ref vvvp) => compute_bbox(vvvp, &|ref vvp| compute_bbox(&vvp[0], &position_bbox)),
-
-            // GeometryCollection is Vec<Geometry>.
-            Value::GeometryCollection(ref geoms) => compute_bbox(geoms, &|ref g| g.to_bbox()),
-        }
-    }
-}
-
-
-// Divide and conquer approach for computing bounding boxes.  This relies on
-// the fact that the bounding box of an array of objects is the merged
-// bounding box of the first half of the array with the bounding box of the
-// second half of the array. We recursively split up the array until we
-// compute the bounding box of a single element, and the combining the
-// bounding boxes to compute the overall bounding box. Computing the bounding
-// box of the individual elements are broken down the same way until we reach
-// a single coordinate (Position) pair.  The final process may have varying
-// levels of nesting depending on the structure of the data.  `func` is
-// supplied to compute the bounding box of a single value. We use different
-// behavior for the same type (such as Vec<Vec<Position>>) depending on the
-// geometry type (i.e., Polygon vs.  MultiLineString).
-fn compute_bbox<T, F>(v: &[T], func: &F) -> Bbox 
-    where F: Fn(&T) -> Bbox + Sync, T: Sync {
-    match v.len() {
-        0 => panic!("No positions!"),
-        1 => func(&v[0]),
-        _ => {
-            let mid = v.len() / 2;
-            let (left, right) = v.split_at(mid);
-            let (left_bbox, right_bbox) = rayon::join(|| compute_bbox(left,
-func), || compute_bbox(right, func));
-            left_bbox.merge(&right_bbox)
-        }
-    }
-}
-
-
-// Open the file specified on the command line.
-// Bail if we're not called correctly or can't open the file.
-fn get_file_or_fail() -> File {
-    let args : Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: $par_bbox /path/to/file.geojson");
+// Load the file specified on the command line or bail if we can't.
+fn load_or_fail(filename: &str) -> Loaded {
+    match load(filename) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Could not read '{}': {}", filename, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+
+// Parse a GeoJSON document from the bytes of an input.
+fn parse_geojson(data: &[u8]) -> Result<GeoJson, ReadError> {
+    serde_json::from_slice(data).map_err(|e| ReadError::from_json(data, e))
+}
+
+
+// Parse an input loaded into memory, as GeoJSON, FlatGeobuf or a Shapefile
+// along with its .shx and .dbf.
+fn parse_input(filename: &str, options: &Options, data: &[u8]) -> Result<GeoJson, ReadError> {
+    match options.input_format(filename) {
+        InputFormat::Shapefile => (),
+        InputFormat::FlatGeobuf => return Ok(GeoJson::FeatureCollection(par_bbox::parse_flatgeobuf(data)?)),
+        _ => return parse_geojson(data),
+    }
+    let shx = sibling(filename, "shx").map(|f| load_input(&f)).transpose()?;
+    let dbf = sibling(filename, "dbf").map(|f| load_input(&f)).transpose()?;
+    let fc = par_bbox::parse_shapefile(data, shx.as_deref(), dbf.as_deref())?;
+    Ok(GeoJson::FeatureCollection(fc))
+}
+
+
+// The file next to `filename` with the given extension, in lower or upper
+// case, if there is one.
+fn sibling(filename: &str, extension: &str) -> Option<String> {
+    [extension.to_string(), extension.to_uppercase()].iter()
+        .map(|ext| Path::new(filename).with_extension(ext))
+        .find(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+
+// Warn if the extent declared in a Shapefile or FlatGeobuf header differs
+// from the computed bbox by more than the tolerance.
+fn check_header(filename: &str, geojson: &GeoJson, total_bbox: &Bbox, options: &Options) {
+    let declared = match *geojson {
+        GeoJson::FeatureCollection(ref fc) => fc.bbox.as_ref(),
+        _ => None,
+    };
+    // There is nothing to compare an empty file's header with.
+    if let (Some(declared), false) = (declared, total_bbox.is_empty()) {
+        let difference = par_bbox::bbox_difference(declared, total_bbox);
+        if difference > options.tolerance {
+            eprintln!("Warning: the header of '{}' declares bbox {:?}, which differs from the computed bbox by {}",
+                      filename, declared, difference);
+        }
+    }
+}
+
+
+// Write the bbox of every feature to stdout.
+fn write_per_feature(geojson: &GeoJson, options: &Options, format: RowFormat) {
+    let single;
+    let fc = match *geojson {
+        GeoJson::FeatureCollection(ref fc) => fc,
+        GeoJson::Feature(ref feature) => {
+            single = FeatureCollection {
+                bbox: None,
+                features: vec![feature.clone()],
+                foreign_members: None,
+            };
+            &single
+        }
+        GeoJson::Geometry(_) => {
+            eprintln!("--per-feature needs a Feature or FeatureCollection");
+            std::process::exit(1);
+        }
+    };
+
+    let mut rows = match par_bbox::feature_bboxes(fc, &options.key) {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Could not compute bbox: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if options.antimeridian {
+        for row in &mut rows {
+            row.bbox = row.bbox.antimeridian();
+        }
+    }
+
+    let stdout = io::stdout();
+    let result = match format {
+        RowFormat::Csv => par_bbox::write_csv(&rows, stdout.lock()),
+        RowFormat::Ndjson => par_bbox::write_ndjson(&rows, stdout.lock()),
+    };
+    if let Err(e) = result {
+        eprintln!("Could not write output: {}", e);
         std::process::exit(1);
     }
+}
 
-    let filename = &args[1];
-    match File::open(&filename) {
-        Ok(f) => f,
+
+// Write the input back out, with bbox members filled in, to the output file
+// or stdout.
+fn write_bbox_members(mut geojson: GeoJson, options: &Options, geometries: bool) {
+    let member_options = MemberOptions { geometries, antimeridian: options.antimeridian };
+    if let Err(e) = par_bbox::fill_bbox_members(&mut geojson, &member_options) {
+        eprintln!("Could not compute bbox: {}", e);
+        std::process::exit(1);
+    }
+
+    let result = match options.output {
+        Some(ref path) => File::create(path).and_then(|mut f| writeln!(f, "{}", geojson)),
+        None => writeln!(io::stdout(), "{}", geojson),
+    };
+    if let Err(e) = result {
+        eprintln!("Could not write output: {}", e);
+        std::process::exit(1);
+    }
+}
+
+
+// Report every bbox member that differs from its computed extent. Exits
+// non-zero if any differ by more than the tolerance.
+fn check(geojson: &GeoJson, options: &Options) {
+    let report = match par_bbox::check_bbox_members(geojson) {
+        Ok(report) => report,
         Err(e) => {
-            println!("Could not open '{}': {}", filename, e.description());
+            eprintln!("Could not compute bbox: {}", e);
             std::process::exit(1);
         }
+    };
+
+    for m in &report.mismatches {
+        let computed = if m.computed.is_empty() { "empty".to_string() }
+                       else { format!("{:?}", m.computed.to_vec()) };
+        println!("{}: declared {:?}, computed {}, difference {}{}",
+                 m.path, m.declared, computed, m.difference,
+                 if m.difference > options.tolerance { " (exceeds tolerance)" } else { "" });
+    }
+
+    let exceeding = report.exceeding(options.tolerance).len();
+    println!("Checked {} bbox member(s): {} mismatch(es), {} exceeding tolerance {}",
+             report.checked, report.mismatches.len(), exceeding, options.tolerance);
+    if exceeding > 0 {
+        std::process::exit(1);
     }
 }
 
 
-fn main() {
-    let mut file = get_file_or_fail();
+// Convert the input to FlatGeobuf, written to the output file or stdout. A
+// single Feature or Geometry becomes a file of one feature.
+fn write_flatgeobuf(geojson: GeoJson, options: &Options) {
+    let features = match geojson {
+        GeoJson::FeatureCollection(fc) => fc.features,
+        GeoJson::Feature(feature) => vec![feature],
+        GeoJson::Geometry(geometry) => vec![Feature {
+            bbox: None,
+            geometry: Some(geometry),
+            id: None,
+            properties: None,
+            foreign_members: None,
+        }],
+    };
+    let fc = FeatureCollection { bbox: None, features, foreign_members: None };
+
+    let result = match options.output {
+        Some(ref path) => File::create(path)
+            .map_err(ReadError::from)
+            .and_then(|f| par_bbox::write_flatgeobuf(&fc, BufWriter::new(f))),
+        None => par_bbox::write_flatgeobuf(&fc, BufWriter::new(io::stdout().lock())),
+    };
+    if let Err(e) = result {
+        eprintln!("Could not write FlatGeobuf: {}", e);
+        std::process::exit(1);
+    }
+}
 
-    // Load the file into a String, then parse. This is faster than
-    // parsing directly from the File.
-    let mut data = String::new();
 
-    let start = PreciseTime::now();
-    println!("Reading file");
-    file.read_to_string(&mut data).unwrap();
-    println!("Parsing JSON");
-    let geojson : GeoJson = data.parse().unwrap();
-    let end_parsed = PreciseTime::now();
-    println!("Parsed.");
+fn seconds(from: PreciseTime, to: PreciseTime) -> f64 {
+    from.to(to).num_microseconds().unwrap() as f64 * 1e-6
+}
+
 
-    let total_bbox = geojson.to_bbox();
+// Print the total bbox, along with what was skipped and how long it took.
+fn print_total(geojson: &GeoJson, options: &Options, loaded: &Loaded, end_parsed: PreciseTime) {
+    let total_bbox = match geojson.to_bbox_with(&options.bbox_options) {
+        Ok(bbox) => bbox,
+        Err(e) => {
+            eprintln!("Could not compute bbox: {}", e);
+            std::process::exit(1);
+        }
+    };
     let end_bbox = PreciseTime::now();
- 
-    println!("Total bbox: {:?}", total_bbox);
-    println!("Time to parse: {}", start.to(end_parsed).num_microseconds().unwrap() as f64 * 1e-6);
-    println!("Time to bbox: {:?}", end_parsed.to(end_bbox).num_microseconds().unwrap() as f64 * 1e-6)
+
+    let filename = &options.filenames[0];
+    if options.input_format(filename).has_header() {
+        check_header(filename, geojson, &total_bbox, options);
+    }
+    print_bbox(total_bbox, Skipped::count(geojson), options);
+    loaded.print_times();
+    eprintln!("Time to parse: {}", seconds(loaded.end(), end_parsed));
+    eprintln!("Time to bbox: {:?}", seconds(end_parsed, end_bbox));
+}
+
+
+// Compute and print the total bbox by scanning the JSON.
+fn scan_total(filename: &str, options: &Options) {
+    eprintln!("Reading file");
+    let loaded = load_or_fail(filename);
+    eprintln!("Scanning JSON");
+    let scanned = match par_bbox::par_scan_bbox(&loaded.data) {
+        Ok(scanned) => scanned,
+        Err(e) => {
+            eprintln!("Could not compute bbox: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let end = PreciseTime::now();
+
+    print_bbox(scanned.bbox, scanned.skipped, options);
+    loaded.print_times();
+    eprintln!("Time to scan and bbox: {}", seconds(loaded.end(), end));
+}
+
+
+// Compute and print the total bbox while reading the file, batch by batch.
+fn stream_total(file: Box<dyn Read + Send>, options: &Options, batch_size: usize) {
+    let start = PreciseTime::now();
+    eprintln!("Streaming features in batches of {}", batch_size);
+    let streamed = match par_bbox::stream_bbox(BufReader::new(file), batch_size) {
+        Ok(streamed) => streamed,
+        Err(e) => {
+            eprintln!("Could not compute bbox: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let end = PreciseTime::now();
+
+    print_bbox(streamed.bbox, streamed.skipped, options);
+    eprintln!("Time to parse and bbox: {}", seconds(start, end));
+}
+
+
+// Compute and print the total bbox of a GeoJSON text sequence, or of WKT
+// or WKB lines.
+fn sequence_total(file: Box<dyn Read + Send>, options: &Options, format: InputFormat) {
+    let start = PreciseTime::now();
+    let result = if format == InputFormat::Wkt {
+        eprintln!("Reading WKT and WKB lines");
+        par_bbox::wkt_bbox(BufReader::new(file), par_bbox::DEFAULT_CHUNK_SIZE)
+    } else {
+        eprintln!("Reading GeoJSON text sequence");
+        par_bbox::sequence_bbox(BufReader::new(file), par_bbox::DEFAULT_CHUNK_SIZE)
+    };
+    let streamed = match result {
+        Ok(streamed) => streamed,
+        Err(e) => {
+            eprintln!("Could not compute bbox: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let end = PreciseTime::now();
+
+    print_bbox(streamed.bbox, streamed.skipped, options);
+    eprintln!("Time to parse and bbox: {}", seconds(start, end));
+}
+
+
+// Compute and print the total bbox of the points in a CSV file.
+fn csv_total(filename: &str, options: &Options) {
+    let start = PreciseTime::now();
+    eprintln!("Reading CSV points");
+    let points = match par_bbox::csv_bbox(open_or_fail(filename), &options.csv, par_bbox::DEFAULT_CHUNK_SIZE) {
+        Ok(points) => points,
+        Err(e) => {
+            eprintln!("Could not compute bbox: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let end = PreciseTime::now();
+
+    print_bbox(points.bbox, Skipped::default(), options);
+    report_rows(filename, &points);
+    eprintln!("Time to parse and bbox: {}", seconds(start, end));
+}
+
+
+// Report the rows of a CSV file that had no point. They don't fail the run.
+fn report_rows(filename: &str, points: &CsvPoints) {
+    if points.blank > 0 {
+        eprintln!("Skipped {} row(s) of '{}' with blank coordinates", points.blank, filename);
+    }
+    if let Some(line) = points.first_unparseable {
+        eprintln!("Warning: skipped {} row(s) of '{}' whose coordinates could not be parsed, the first on line {}",
+                  points.unparseable, filename, line);
+    }
+}
+
+
+fn print_bbox(mut total_bbox: Bbox, skipped: Skipped, options: &Options) {
+    if options.antimeridian {
+        total_bbox = total_bbox.antimeridian();
+    }
+
+    if let Some(format) = options.format {
+        if let Err(e) = par_bbox::write_bbox(&total_bbox, format, io::stdout()) {
+            eprintln!("Could not write output: {}", e);
+            std::process::exit(1);
+        }
+        if total_bbox.is_empty() {
+            eprintln!("Total bbox: empty (no coordinates found)");
+        }
+    } else if total_bbox.is_empty() {
+        println!("Total bbox: empty (no coordinates found)");
+    } else {
+        println!("Total bbox: {:?}", total_bbox);
+    }
+
+    if !total_bbox.is_empty() && total_bbox.is_mixed_dimension() {
+        eprintln!("Warning: input mixes 2D and 3D positions; the z range covers the 3D positions only");
+    }
+
+    if skipped.total() > 0 {
+        eprintln!("Skipped {} feature(s): {} with null geometry, {} with empty geometry",
+                 skipped.total(), skipped.null_geometry, skipped.empty_geometry);
+    }
+}
+
+
+// Compute the total bbox of one input, whatever its format.
+fn input_total(filename: &str, options: &Options) -> Result<Streamed, ReadError> {
+    match (options.input_format(filename), options.stream) {
+        (InputFormat::Shapefile, _) | (InputFormat::FlatGeobuf, _) => {
+            let geojson = parse_input(filename, options, &load(filename)?.data)?;
+            let bbox = geojson.to_bbox_with(&options.bbox_options)?;
+            check_header(filename, &geojson, &bbox, options);
+            Ok(Streamed { bbox, skipped: Skipped::count(&geojson) })
+        }
+        (InputFormat::Sequence, _) => {
+            par_bbox::sequence_bbox(BufReader::new(open_input(filename)?), par_bbox::DEFAULT_CHUNK_SIZE)
+        }
+        (InputFormat::Wkt, _) => {
+            par_bbox::wkt_bbox(BufReader::new(open_input(filename)?), par_bbox::DEFAULT_CHUNK_SIZE)
+        }
+        (InputFormat::Csv, _) => {
+            let points = par_bbox::csv_bbox(open_input(filename)?, &options.csv, par_bbox::DEFAULT_CHUNK_SIZE)?;
+            report_rows(filename, &points);
+            Ok(Streamed { bbox: points.bbox, skipped: Skipped::default() })
+        }
+        (InputFormat::GeoJson, Some(batch_size)) => {
+            par_bbox::stream_bbox(BufReader::new(open_input(filename)?), batch_size)
+        }
+        (InputFormat::GeoJson, None) if options.scan => {
+            let data = load(filename)?.data;
+            par_bbox::par_scan_bbox(&data)
+        }
+        (InputFormat::GeoJson, None) => {
+            let geojson = parse_geojson(&load(filename)?.data)?;
+            Ok(Streamed { bbox: geojson.to_bbox_with(&options.bbox_options)?, skipped: Skipped::count(&geojson) })
+        }
+    }
+}
+
+
+// Summarize one phase's timings, in seconds, as JSON.
+fn phase_stats(samples: &[f64]) -> serde_json::Value {
+    match PhaseStats::new(samples) {
+        Some(stats) => json!({"min": stats.min, "median": stats.median, "p95": stats.p95, "max": stats.max}),
+        None => serde_json::Value::Null,
+    }
+}
+
+
+// Read, parse and compute the bbox `repeats` times for each thread count,
+// and print the statistics of each phase as JSON.
+fn bench(options: &Options, repeats: usize) {
+    let filename = &options.filenames[0];
+    let thread_counts = if options.bench_threads.is_empty() {
+        vec![rayon::current_num_threads()]
+    } else {
+        options.bench_threads.clone()
+    };
+
+    let mut runs = Vec::new();
+    let mut total_bbox = Bbox::empty();
+    for &threads in &thread_counts {
+        let pool = match par_bbox::thread_pool(threads) {
+            Ok(pool) => pool,
+            Err(e) => {
+                eprintln!("Could not start {} threads: {}", threads, e);
+                std::process::exit(1);
+            }
+        };
+        eprintln!("Running {} repeat(s) with {} thread(s)", repeats, threads);
+
+        let (mut read, mut decompress, mut parse, mut bbox) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for _ in 0..repeats {
+            let loaded = load_or_fail(filename);
+            let geojson = match parse_input(filename, options, &loaded.data) {
+                Ok(geojson) => geojson,
+                Err(e) => {
+                    eprintln!("Could not parse '{}': {}", filename, e);
+                    std::process::exit(1);
+                }
+            };
+            let end_parsed = PreciseTime::now();
+            total_bbox = match geojson.to_bbox_in(&pool, &options.bbox_options) {
+                Ok(bbox) => bbox,
+                Err(e) => {
+                    eprintln!("Could not compute bbox: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let end_bbox = PreciseTime::now();
+
+            read.push(seconds(loaded.start, loaded.end_read));
+            if let Some(end) = loaded.end_decompressed {
+                decompress.push(seconds(loaded.end_read, end));
+            }
+            parse.push(seconds(loaded.end(), end_parsed));
+            bbox.push(seconds(end_parsed, end_bbox));
+        }
+        let mut run = json!({
+            "threads": threads,
+            "read": phase_stats(&read),
+            "parse": phase_stats(&parse),
+            "bbox": phase_stats(&bbox),
+        });
+        if !decompress.is_empty() {
+            run["decompress"] = phase_stats(&decompress);
+        }
+        runs.push(run);
+    }
+
+    let strategy = format!("{:?}", options.bbox_options.strategy).to_lowercase();
+    let bbox = if total_bbox.is_empty() { serde_json::Value::Null } else { json!(total_bbox.to_vec()) };
+    let report = json!({
+        "file": filename,
+        "repeats": repeats,
+        "strategy": strategy,
+        "bbox": bbox,
+        "runs": runs,
+    });
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+}
+
+
+// Compute the bbox of every input in parallel and print each one, followed
+// by their union. Exits non-zero if any input failed.
+fn print_totals(options: &Options) {
+    let start = PreciseTime::now();
+    let results: Vec<_> = options.filenames.par_iter()
+        .map(|filename| input_total(filename, options))
+        .collect();
+    let end = PreciseTime::now();
+
+    let mut union = Streamed { bbox: Bbox::empty(), skipped: Skipped::default() };
+    let mut failed = 0;
+    for (filename, result) in options.filenames.iter().zip(results) {
+        match result {
+            Ok(streamed) => {
+                let bbox = if options.antimeridian { streamed.bbox.antimeridian() } else { streamed.bbox };
+                let line = if bbox.is_empty() { format!("{}: empty", filename) }
+                           else { format!("{}: {:?}", filename, bbox) };
+                // Only the total is written in a structured format.
+                if options.format.is_some() { eprintln!("{}", line) } else { println!("{}", line) }
+                union.bbox = union.bbox.merge(&streamed.bbox);
+                union.skipped = union.skipped.merge(&streamed.skipped);
+            }
+            Err(e) => {
+                eprintln!("{}: could not compute bbox: {}", filename, e);
+                failed += 1;
+            }
+        }
+    }
+
+    print_bbox(union.bbox, union.skipped, options);
+    eprintln!("Time to read {} file(s): {}", options.filenames.len(), seconds(start, end));
+    if failed > 0 {
+        eprintln!("{} file(s) failed", failed);
+        std::process::exit(1);
+    }
+}
+
+
+fn main() {
+    let options = parse_args();
+    if let Some(threads) = options.threads {
+        #[allow(deprecated)]
+        if let Err(e) = rayon::initialize(rayon::Configuration::new().num_threads(threads)) {
+            eprintln!("Could not start {} threads: {}", threads, e);
+            std::process::exit(1);
+        }
+    }
+    if options.filenames.len() > 1 {
+        print_totals(&options);
+        return;
+    }
+
+    if let Mode::Bench { repeats } = options.mode {
+        bench(&options, repeats);
+        return;
+    }
+
+    let filename = &options.filenames[0];
+    let format = options.input_format(filename);
+    if format == InputFormat::Sequence || format == InputFormat::Wkt {
+        sequence_total(open_or_fail(filename), &options, format);
+        return;
+    }
+    if format == InputFormat::Csv {
+        csv_total(filename, &options);
+        return;
+    }
+    if let Some(batch_size) = options.stream {
+        stream_total(open_or_fail(filename), &options, batch_size);
+        return;
+    }
+    if options.scan {
+        scan_total(filename, &options);
+        return;
+    }
+
+    // Map the file into memory, or read stdin, decompress it if need be,
+    // then parse from the bytes. Progress messages and timings go to stderr,
+    // keeping stdout for the output itself.
+    eprintln!("Reading file");
+    let loaded = load_or_fail(filename);
+    eprintln!("Parsing {}", match options.input_format(filename) {
+        InputFormat::Shapefile => "Shapefile",
+        InputFormat::FlatGeobuf => "FlatGeobuf",
+        _ => "JSON",
+    });
+    let geojson = match parse_input(filename, &options, &loaded.data) {
+        Ok(geojson) => geojson,
+        Err(e) => {
+            eprintln!("Could not parse '{}': {}", filename, e);
+            std::process::exit(1);
+        }
+    };
+    let end_parsed = PreciseTime::now();
+    eprintln!("Parsed.");
+
+    match options.mode {
+        Mode::Total => print_total(&geojson, &options, &loaded, end_parsed),
+        Mode::PerFeature(format) => write_per_feature(&geojson, &options, format),
+        Mode::WriteBbox { geometries } => write_bbox_members(geojson, &options, geometries),
+        Mode::Check => check(&geojson, &options),
+        Mode::ToFlatGeobuf => write_flatgeobuf(geojson, &options),
+        Mode::Bench { .. } => unreachable!(),
+    }
+    if options.mode != Mode::Total {
+        let end_bbox = PreciseTime::now();
+        loaded.print_times();
+        eprintln!("Time to parse: {}", seconds(loaded.end(), end_parsed));
+        eprintln!("Time to bbox: {:?}", seconds(end_parsed, end_bbox));
+    }
 }
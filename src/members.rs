@@ -0,0 +1,115 @@
+use rayon::prelude::*;
+
+use geojson::{Feature, FeatureCollection, GeoJson, Geometry, Value};
+
+use bbox::Bbox;
+use error::BboxError;
+use to_bbox::ToBbox;
+
+
+/// Which RFC 7946 "bbox" members `fill_bbox_members` writes, and how.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemberOptions {
+    /// Also write a bbox on every Geometry, including the members of
+    /// GeometryCollections. FeatureCollections, Features and a Geometry at
+    /// the root always get one. Otherwise the bbox members of Geometries are
+    /// removed, as they would no longer be checked.
+    pub geometries: bool,
+    /// Write antimeridian-aware bboxes; see Bbox::antimeridian.
+    pub antimeridian: bool,
+}
+
+
+/// Fill in the "bbox" members of a GeoJSON object from its computed extents,
+/// replacing any that are already there. Objects with no coordinates have
+/// their bbox member removed, since an empty extent cannot be written.
+///
+/// Features are processed in parallel and the FeatureCollection's bbox is
+/// the merge of its features' bboxes, so each position is only visited once.
+pub fn fill_bbox_members(geojson: &mut GeoJson, options: &MemberOptions) -> Result<(), BboxError> {
+    match *geojson {
+        GeoJson::Geometry(ref mut geometry) => {
+            let bbox = fill_geometry(geometry, options)?;
+            geometry.bbox = member(&bbox, options);
+            Ok(())
+        }
+        GeoJson::Feature(ref mut feature) => fill_feature(feature, options).map(|_| ()),
+        GeoJson::FeatureCollection(ref mut fc) => fill_feature_collection(fc, options),
+    }
+}
+
+
+fn fill_feature_collection(fc: &mut FeatureCollection, options: &MemberOptions) -> Result<(), BboxError> {
+    let results: Vec<Result<Bbox, BboxError>> = fc.features.par_iter_mut()
+        .enumerate()
+        .map(|(index, feature)| {
+            fill_feature(feature, options).map_err(|e| e.at_index(index).in_field("features"))
+        })
+        .collect();
+
+    let mut total = Bbox::empty();
+    for result in results {
+        total = total.merge(&result?);
+    }
+    fc.bbox = member(&total, options);
+    Ok(())
+}
+
+
+fn fill_feature(feature: &mut Feature, options: &MemberOptions) -> Result<Bbox, BboxError> {
+    let bbox = match feature.geometry {
+        Some(ref mut geometry) => {
+            fill_geometry(geometry, options).map_err(|e| e.in_field("geometry"))?
+        }
+        None => Bbox::empty(),
+    };
+    feature.bbox = member(&bbox, options);
+    Ok(bbox)
+}
+
+
+// Without options.geometries this is Geometry::to_bbox, after removing any
+// bbox members. Otherwise the members of a GeometryCollection are filled in
+// individually and merged.
+fn fill_geometry(geometry: &mut Geometry, options: &MemberOptions) -> Result<Bbox, BboxError> {
+    if !options.geometries {
+        remove_members(geometry);
+        return geometry.to_bbox();
+    }
+
+    let bbox = match geometry.value {
+        Value::GeometryCollection(ref mut geoms) => {
+            let mut bbox = Bbox::empty();
+            for (index, g) in geoms.iter_mut().enumerate() {
+                let b = fill_geometry(g, options)
+                    .map_err(|e| e.at_index(index).in_field("geometries"))?;
+                bbox = bbox.merge(&b);
+            }
+            bbox
+        }
+        ref value => value.to_bbox()?,
+    };
+    geometry.bbox = member(&bbox, options);
+    Ok(bbox)
+}
+
+
+fn remove_members(geometry: &mut Geometry) {
+    geometry.bbox = None;
+    if let Value::GeometryCollection(ref mut geoms) = geometry.value {
+        for g in geoms {
+            remove_members(g);
+        }
+    }
+}
+
+
+fn member(bbox: &Bbox, options: &MemberOptions) -> Option<Vec<f64>> {
+    if bbox.is_empty() {
+        None
+    } else if options.antimeridian {
+        Some(bbox.antimeridian().to_vec())
+    } else {
+        Some(bbox.to_vec())
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::{self, Write};
+
+use serde_json::{self, Value as JsonValue};
+
+use bbox::Bbox;
+
+
+/// A machine-readable way of writing a single bbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BboxFormat {
+    /// RFC 7946 bbox array, `[xmin, ymin, xmax, ymax]`.
+    Json,
+    /// A WKT POLYGON.
+    Wkt,
+    /// A GeoJSON Feature with the bbox as its Polygon geometry.
+    GeoJson,
+    /// A header and one CSV row.
+    Csv,
+    /// `-te xmin ymin xmax ymax`, as taken by GDAL's utilities.
+    Gdal,
+}
+
+
+impl BboxFormat {
+    /// Look up a format by the name used on the command line: json, wkt,
+    /// geojson, csv or gdal.
+    pub fn from_name(name: &str) -> Option<BboxFormat> {
+        match name {
+            "json" => Some(BboxFormat::Json),
+            "wkt" => Some(BboxFormat::Wkt),
+            "geojson" => Some(BboxFormat::GeoJson),
+            "csv" => Some(BboxFormat::Csv),
+            "gdal" => Some(BboxFormat::Gdal),
+            _ => None,
+        }
+    }
+}
+
+
+/// Write a bbox in the given format, followed by a newline.
+///
+/// The JSON array and the GeoJSON Feature's "bbox" member include the z
+/// range when there is one, as do the CSV columns; the polygons are 2D. A
+/// bbox that crosses the antimeridian is written as a MultiPolygon split at
+/// the antimeridian (RFC 7946 section 3.1.9), while the other formats give
+/// xmin > xmax. An empty bbox is written as `null`, `POLYGON EMPTY`, a
+/// Feature with a null geometry or blank CSV extents; there is no GDAL form
+/// of it so nothing is written.
+pub fn write_bbox<W: Write>(bbox: &Bbox, format: BboxFormat, mut out: W) -> io::Result<()> {
+    match format {
+        BboxFormat::Json => {
+            let value = if bbox.is_empty() { JsonValue::Null } else { json!(bbox.to_vec()) };
+            serde_json::to_writer(&mut out, &value)?;
+            writeln!(out)
+        }
+        BboxFormat::Wkt => writeln!(out, "{}", wkt(bbox)),
+        BboxFormat::GeoJson => {
+            let feature = if bbox.is_empty() {
+                json!({"type": "Feature", "properties": {}, "geometry": null})
+            } else {
+                json!({"type": "Feature", "bbox": bbox.to_vec(), "properties": {},
+                       "geometry": geometry(bbox)})
+            };
+            serde_json::to_writer(&mut out, &feature)?;
+            writeln!(out)
+        }
+        BboxFormat::Csv => {
+            let z = bbox.has_z();
+            writeln!(out, "xmin,ymin,xmax,ymax{}", if z { ",zmin,zmax" } else { "" })?;
+            if bbox.is_empty() {
+                writeln!(out, ",,,")
+            } else if z {
+                writeln!(out, "{},{},{},{},{},{}",
+                         bbox.xmin(), bbox.ymin(), bbox.xmax(), bbox.ymax(), bbox.zmin(), bbox.zmax())
+            } else {
+                writeln!(out, "{},{},{},{}", bbox.xmin(), bbox.ymin(), bbox.xmax(), bbox.ymax())
+            }
+        }
+        BboxFormat::Gdal => {
+            if bbox.is_empty() {
+                return Ok(());
+            }
+            writeln!(out, "-te {} {} {} {}", bbox.xmin(), bbox.ymin(), bbox.xmax(), bbox.ymax())
+        }
+    }
+}
+
+
+// The exterior ring of each polygon covering the bbox, counter-clockwise:
+// one, or two if it crosses the antimeridian.
+fn rings(bbox: &Bbox) -> Vec<Vec<[f64; 2]>> {
+    let ring = |xmin: f64, xmax: f64| {
+        vec![[xmin, bbox.ymin()], [xmax, bbox.ymin()], [xmax, bbox.ymax()],
+             [xmin, bbox.ymax()], [xmin, bbox.ymin()]]
+    };
+    if bbox.crosses_antimeridian() {
+        vec![ring(bbox.xmin(), 180.0), ring(-180.0, bbox.xmax())]
+    } else {
+        vec![ring(bbox.xmin(), bbox.xmax())]
+    }
+}
+
+
+fn geometry(bbox: &Bbox) -> JsonValue {
+    let rings = rings(bbox);
+    if rings.len() == 1 {
+        json!({"type": "Polygon", "coordinates": rings})
+    } else {
+        let polygons: Vec<_> = rings.into_iter().map(|ring| vec![ring]).collect();
+        json!({"type": "MultiPolygon", "coordinates": polygons})
+    }
+}
+
+
+fn wkt(bbox: &Bbox) -> String {
+    if bbox.is_empty() {
+        return "POLYGON EMPTY".to_string();
+    }
+    let polygons: Vec<String> = rings(bbox).iter()
+        .map(|ring| {
+            let points: Vec<String> = ring.iter().map(|p| format!("{} {}", p[0], p[1])).collect();
+            format!("(({}))", points.join(", "))
+        })
+        .collect();
+    if polygons.len() == 1 {
+        format!("POLYGON {}", polygons[0])
+    } else {
+        format!("MULTIPOLYGON ({})", polygons.join(", "))
+    }
+}
@@ -0,0 +1,553 @@
+use std::fmt;
+use std::mem;
+
+use rayon;
+use rayon::prelude::*;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{self, Value as JsonValue};
+
+use geojson::GeoJson;
+
+use bbox::Bbox;
+use error::{BboxError, BboxErrorKind, ReadError};
+use skipped::Skipped;
+use split;
+use stream::Streamed;
+use to_bbox::ToBbox;
+
+
+/// Compute the bounding box of a GeoJSON document straight from its bytes,
+/// without building the `geojson` types. Positions are never collected into
+/// vectors; only the running min/max is kept, and members that have no
+/// bearing on the bbox, such as "properties", are skipped over.
+///
+/// The result is the same as parsing the document and calling `to_bbox`,
+/// with the same rules for each geometry type (only the outer ring of a
+/// polygon counts), the same skipped features and the same error paths. The
+/// one exception to the no-allocation rule is a geometry whose
+/// "coordinates" come before its "type": those coordinates are parsed into
+/// a JSON value until the type says how to read them.
+///
+/// Less is validated than by the `geojson` parser. Members the bbox does not
+/// need are not checked, and a missing "geometry" is treated as null.
+pub fn scan_bbox(data: &[u8]) -> Result<Streamed, ReadError> {
+    let scanned = scan_object(data).map_err(|e| ReadError::from_json(data, e))?;
+    Ok(Streamed { bbox: scanned.bbox?, skipped: scanned.skipped })
+}
+
+
+/// `scan_bbox`, scanning the features of a FeatureCollection in parallel.
+///
+/// The "features" array is first split into its elements with a quick pass
+/// over the bytes that only follows strings and brackets. The elements are
+/// then grouped into chunks of roughly equal size in bytes, each chunk is
+/// scanned on the rayon pool, and the chunk bboxes are merged with
+/// `Bbox::merge`. The rest of the document is scanned on its own.
+///
+/// The result is the same as that of `scan_bbox`, except that the line and
+/// column of a JSON syntax error inside a feature are counted from the
+/// start of that feature. Documents that are not FeatureCollections, or
+/// whose structure cannot be followed, are simply handed to `scan_bbox`.
+pub fn par_scan_bbox(data: &[u8]) -> Result<Streamed, ReadError> {
+    let (array, elements) = match split::features_array(data) {
+        Some(found) => found,
+        None => return scan_bbox(data),
+    };
+
+    // Everything but the features, to check that this is a FeatureCollection
+    // and that the rest of it is valid.
+    let mut rest = Vec::with_capacity(data.len() - array.len() + 2);
+    rest.extend_from_slice(&data[..array.start]);
+    rest.extend_from_slice(b"[]");
+    rest.extend_from_slice(&data[array.end..]);
+    if !scan_object(&rest).map_err(|e| ReadError::from_json(data, e))?.feature_collection {
+        return scan_bbox(data);
+    }
+
+    let chunk_bytes = (array.len() / (rayon::current_num_threads() * CHUNKS_PER_THREAD)).max(MIN_CHUNK_BYTES);
+    let chunks = split::chunks(&elements, chunk_bytes);
+    let scanned: Vec<Result<Scanned, serde_json::Error>> = chunks.par_iter()
+        .map(|&(first, ranges)| {
+            let mut chunk = Scanned::geometry(Ok(Bbox::empty()));
+            for (i, range) in ranges.iter().enumerate() {
+                chunk.push_feature(first + i, scan_object(&data[range.clone()])?);
+            }
+            Ok(chunk)
+        })
+        .collect();
+
+    // Syntax errors come first, as they would stop scan_bbox.
+    let mut total = Scanned::geometry(Ok(Bbox::empty()));
+    for chunk in scanned {
+        let chunk = chunk.map_err(|e| ReadError::from_json(data, e))?;
+        let bbox = mem::replace(&mut total.bbox, Ok(Bbox::empty()));
+        total.bbox = merge_results(bbox, chunk.bbox);
+        total.skipped = total.skipped.merge(&chunk.skipped);
+    }
+    Ok(Streamed { bbox: total.bbox.map_err(|e| e.in_field("features"))?, skipped: total.skipped })
+}
+
+
+// The smallest chunk of features par_scan_bbox scans in one go, in bytes.
+const MIN_CHUNK_BYTES: usize = 1 << 16;
+
+// How many chunks per thread par_scan_bbox aims for.
+const CHUNKS_PER_THREAD: usize = 4;
+
+
+// Scan a whole JSON text holding one GeoJSON object.
+fn scan_object(data: &[u8]) -> Result<Scanned, serde_json::Error> {
+    let mut deserializer = serde_json::Deserializer::from_slice(data);
+    let scanned = ObjectSeed.deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(scanned)
+}
+
+
+type BboxResult = Result<Bbox, BboxError>;
+
+
+// Merge two results, keeping the first error.
+fn merge_results(left: BboxResult, right: BboxResult) -> BboxResult {
+    Ok(left?.merge(&right?))
+}
+
+
+// What a scanned object contributes.
+struct Scanned {
+    bbox: BboxResult,
+    skipped: Skipped,
+    feature_collection: bool,
+}
+
+
+impl Scanned {
+    fn geometry(bbox: BboxResult) -> Scanned {
+        Scanned { bbox, skipped: Skipped::default(), feature_collection: false }
+    }
+
+    // Add the next feature of an array, which is at `index`.
+    fn push_feature(&mut self, index: usize, feature: Scanned) {
+        let bbox = mem::replace(&mut self.bbox, Ok(Bbox::empty()));
+        self.bbox = merge_results(bbox, feature.bbox.map_err(|e| e.at_index(index)));
+        self.skipped = self.skipped.merge(&feature.skipped);
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GeometryType {
+    Point,
+    MultiPoint,
+    LineString,
+    MultiLineString,
+    Polygon,
+    MultiPolygon,
+    GeometryCollection,
+}
+
+
+impl GeometryType {
+    fn name(&self) -> &'static str {
+        match *self {
+            GeometryType::Point => "Point",
+            GeometryType::MultiPoint => "MultiPoint",
+            GeometryType::LineString => "LineString",
+            GeometryType::MultiLineString => "MultiLineString",
+            GeometryType::Polygon => "Polygon",
+            GeometryType::MultiPolygon => "MultiPolygon",
+            GeometryType::GeometryCollection => "GeometryCollection",
+        }
+    }
+}
+
+
+// The "type" member of an object.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    FeatureCollection,
+    Feature,
+    Geometry(GeometryType),
+}
+
+
+impl<'de> Deserialize<'de> for Kind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Kind, D::Error> {
+        struct KindVisitor;
+
+        impl<'de> Visitor<'de> for KindVisitor {
+            type Value = Kind;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a GeoJSON type")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Kind, E> {
+                Ok(match s {
+                    "FeatureCollection" => Kind::FeatureCollection,
+                    "Feature" => Kind::Feature,
+                    "Point" => Kind::Geometry(GeometryType::Point),
+                    "MultiPoint" => Kind::Geometry(GeometryType::MultiPoint),
+                    "LineString" => Kind::Geometry(GeometryType::LineString),
+                    "MultiLineString" => Kind::Geometry(GeometryType::MultiLineString),
+                    "Polygon" => Kind::Geometry(GeometryType::Polygon),
+                    "MultiPolygon" => Kind::Geometry(GeometryType::MultiPolygon),
+                    "GeometryCollection" => Kind::Geometry(GeometryType::GeometryCollection),
+                    _ => return Err(E::custom(format!("unknown GeoJSON type \"{}\"", s))),
+                })
+            }
+        }
+
+        deserializer.deserialize_str(KindVisitor)
+    }
+}
+
+
+// The members of an object that matter for its bbox.
+enum Key {
+    Type,
+    Features,
+    Geometry,
+    Coordinates,
+    Geometries,
+    Other,
+}
+
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Key, D::Error> {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = Key;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a member name")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Key, E> {
+                Ok(match s {
+                    "type" => Key::Type,
+                    "features" => Key::Features,
+                    "geometry" => Key::Geometry,
+                    "coordinates" => Key::Coordinates,
+                    "geometries" => Key::Geometries,
+                    _ => Key::Other,
+                })
+            }
+        }
+
+        deserializer.deserialize_str(KeyVisitor)
+    }
+}
+
+
+// A geometry's "coordinates", either already reduced to a bbox or, if they
+// came before the "type", kept until it is known.
+enum Coordinates {
+    Scanned(BboxResult),
+    Deferred(JsonValue),
+}
+
+
+// A single position. Like ToBbox for Position, it needs at least two
+// elements and anything after the third is ignored.
+#[derive(Clone, Copy)]
+struct PositionSeed;
+
+
+impl<'de> DeserializeSeed<'de> for PositionSeed {
+    type Value = BboxResult;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<BboxResult, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+
+impl<'de> Visitor<'de> for PositionSeed {
+    type Value = BboxResult;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a position")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<BboxResult, A::Error> {
+        let mut xyz = [0.0; 3];
+        let mut len = 0;
+        while let Some(value) = seq.next_element::<f64>()? {
+            if len < xyz.len() {
+                xyz[len] = value;
+            }
+            len += 1;
+        }
+        Ok(match len {
+            0 | 1 => Err(BboxError::new(BboxErrorKind::ShortPosition(len))),
+            2 => Ok(Bbox::from_point(xyz[0], xyz[1])),
+            _ => Ok(Bbox::from_point_3d(xyz[0], xyz[1], xyz[2])),
+        })
+    }
+}
+
+
+// An array whose elements are each read with the inner seed and merged.
+// Errors get the element's index, and the first one wins.
+#[derive(Clone, Copy)]
+struct ArraySeed<S>(S);
+
+
+impl<'de, S> DeserializeSeed<'de> for ArraySeed<S>
+    where S: DeserializeSeed<'de, Value = BboxResult> + Copy {
+    type Value = BboxResult;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<BboxResult, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+
+impl<'de, S> Visitor<'de> for ArraySeed<S>
+    where S: DeserializeSeed<'de, Value = BboxResult> + Copy {
+    type Value = BboxResult;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<BboxResult, A::Error> {
+        let mut bbox = Ok(Bbox::empty());
+        let mut index = 0;
+        while let Some(result) = seq.next_element_seed(self.0)? {
+            bbox = merge_results(bbox, result.map_err(|e| e.at_index(index)));
+            index += 1;
+        }
+        Ok(bbox)
+    }
+}
+
+
+// The rings of a polygon. Only the first, outer ring is read; the rest are
+// skipped.
+#[derive(Clone, Copy)]
+struct OuterRingSeed;
+
+
+impl<'de> DeserializeSeed<'de> for OuterRingSeed {
+    type Value = BboxResult;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<BboxResult, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+
+impl<'de> Visitor<'de> for OuterRingSeed {
+    type Value = BboxResult;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of linear rings")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<BboxResult, A::Error> {
+        let bbox = match seq.next_element_seed(ArraySeed(PositionSeed))? {
+            Some(ring) => ring.map_err(|e| e.at_index(0)),
+            None => return Ok(Ok(Bbox::empty())),
+        };
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(bbox)
+    }
+}
+
+
+// A geometry inside a GeometryCollection.
+#[derive(Clone, Copy)]
+struct GeometrySeed;
+
+
+impl<'de> DeserializeSeed<'de> for GeometrySeed {
+    type Value = BboxResult;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<BboxResult, D::Error> {
+        ObjectSeed.deserialize(deserializer).map(|scanned| scanned.bbox)
+    }
+}
+
+
+// The "features" of a FeatureCollection, merging their bboxes and skipped
+// counts.
+struct FeaturesSeed;
+
+
+impl<'de> DeserializeSeed<'de> for FeaturesSeed {
+    type Value = Scanned;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Scanned, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+
+impl<'de> Visitor<'de> for FeaturesSeed {
+    type Value = Scanned;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of GeoJSON Features")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Scanned, A::Error> {
+        let mut total = Scanned::geometry(Ok(Bbox::empty()));
+        let mut index = 0;
+        while let Some(feature) = seq.next_element_seed(ObjectSeed)? {
+            total.push_feature(index, feature);
+            index += 1;
+        }
+        Ok(total)
+    }
+}
+
+
+// A Feature's "geometry", which may be null.
+struct NullableObjectSeed;
+
+
+impl<'de> DeserializeSeed<'de> for NullableObjectSeed {
+    type Value = Option<Scanned>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<Scanned>, D::Error> {
+        deserializer.deserialize_option(self)
+    }
+}
+
+
+impl<'de> Visitor<'de> for NullableObjectSeed {
+    type Value = Option<Scanned>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a GeoJSON Geometry or null")
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Option<Scanned>, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Option<Scanned>, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<Scanned>, D::Error> {
+        ObjectSeed.deserialize(deserializer).map(Some)
+    }
+}
+
+
+// Any GeoJSON object. Members are read according to the "type" if it has
+// been seen, and otherwise kept in case they turn out to matter.
+#[derive(Clone, Copy)]
+struct ObjectSeed;
+
+
+impl<'de> DeserializeSeed<'de> for ObjectSeed {
+    type Value = Scanned;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Scanned, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+
+impl<'de> Visitor<'de> for ObjectSeed {
+    type Value = Scanned;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a GeoJSON object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Scanned, A::Error> {
+        let mut kind = None;
+        let mut features = None;
+        let mut geometry = None;
+        let mut coordinates = None;
+        let mut geometries = None;
+
+        // Whether a member for objects of type `k` is worth reading.
+        let wanted = |kind: Option<Kind>, k: Kind| kind.is_none() || kind == Some(k);
+
+        while let Some(key) = map.next_key::<Key>()? {
+            match key {
+                Key::Type => kind = Some(map.next_value::<Kind>()?),
+                Key::Features if wanted(kind, Kind::FeatureCollection) => {
+                    features = Some(map.next_value_seed(FeaturesSeed)?);
+                }
+                Key::Geometry if wanted(kind, Kind::Feature) => {
+                    geometry = Some(map.next_value_seed(NullableObjectSeed)?);
+                }
+                Key::Geometries if wanted(kind, Kind::Geometry(GeometryType::GeometryCollection)) => {
+                    geometries = Some(map.next_value_seed(ArraySeed(GeometrySeed))?);
+                }
+                Key::Coordinates => match kind {
+                    Some(Kind::Geometry(t)) => {
+                        coordinates = Some(Coordinates::Scanned(next_coordinates(&mut map, t)?));
+                    }
+                    None => coordinates = Some(Coordinates::Deferred(map.next_value()?)),
+                    Some(_) => { map.next_value::<IgnoredAny>()?; }
+                },
+                _ => { map.next_value::<IgnoredAny>()?; }
+            }
+        }
+
+        match kind {
+            None => Err(de::Error::missing_field("type")),
+            Some(Kind::FeatureCollection) => {
+                let features = features.unwrap_or_else(|| Scanned::geometry(Ok(Bbox::empty())));
+                Ok(Scanned { bbox: features.bbox.map_err(|e| e.in_field("features")),
+                             skipped: features.skipped,
+                             feature_collection: true })
+            }
+            Some(Kind::Feature) => {
+                let mut skipped = Skipped::default();
+                let bbox = match geometry {
+                    Some(Some(geometry)) => {
+                        if geometry.bbox.as_ref().is_ok_and(|b| b.is_empty()) {
+                            skipped.empty_geometry += 1;
+                        }
+                        geometry.bbox.map_err(|e| e.in_field("geometry"))
+                    }
+                    _ => {
+                        skipped.null_geometry += 1;
+                        Ok(Bbox::empty())
+                    }
+                };
+                Ok(Scanned { bbox, skipped, feature_collection: false })
+            }
+            Some(Kind::Geometry(GeometryType::GeometryCollection)) => {
+                let bbox = geometries.unwrap_or(Ok(Bbox::empty()));
+                Ok(Scanned::geometry(bbox.map_err(|e| e.in_field("geometries"))))
+            }
+            Some(Kind::Geometry(t)) => match coordinates {
+                Some(Coordinates::Scanned(bbox)) => {
+                    Ok(Scanned::geometry(bbox.map_err(|e| e.in_field("coordinates"))))
+                }
+                Some(Coordinates::Deferred(value)) => {
+                    let geojson = GeoJson::deserialize(json!({"type": t.name(), "coordinates": value}))
+                        .map_err(de::Error::custom)?;
+                    Ok(Scanned::geometry(geojson.to_bbox()))
+                }
+                None => Err(de::Error::missing_field("coordinates")),
+            },
+        }
+    }
+}
+
+
+// Read "coordinates" the way ToBbox for Value does for the given type.
+fn next_coordinates<'de, A: MapAccess<'de>>(map: &mut A, t: GeometryType) -> Result<BboxResult, A::Error> {
+    match t {
+        GeometryType::Point => map.next_value_seed(PositionSeed),
+        GeometryType::MultiPoint | GeometryType::LineString => map.next_value_seed(ArraySeed(PositionSeed)),
+        GeometryType::MultiLineString => map.next_value_seed(ArraySeed(ArraySeed(PositionSeed))),
+        GeometryType::Polygon => map.next_value_seed(OuterRingSeed),
+        GeometryType::MultiPolygon => map.next_value_seed(ArraySeed(OuterRingSeed)),
+        GeometryType::GeometryCollection => map.next_value::<IgnoredAny>().map(|_| Ok(Bbox::empty())),
+    }
+}
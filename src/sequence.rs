@@ -0,0 +1,121 @@
+use std::io::BufRead;
+use std::mem;
+
+use rayon;
+use rayon::prelude::*;
+use serde_json;
+
+use geojson::GeoJson;
+
+use bbox::Bbox;
+use error::ReadError;
+use skipped::Skipped;
+use stream::Streamed;
+use to_bbox::ToBbox;
+
+
+/// The ASCII record separator that starts each text in an RFC 8142 GeoJSON
+/// text sequence.
+pub const RECORD_SEPARATOR: u8 = 0x1E;
+
+/// The number of records `sequence_bbox` reads before handing them off.
+pub const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+
+// A record's number (its line number for newline-delimited input) and text.
+type Record = (usize, Vec<u8>);
+
+
+/// Compute the total bounding box of a sequence of GeoJSON texts, either
+/// newline-delimited (one text per line) or an RFC 8142 GeoJSON text
+/// sequence (each text preceded by a record separator). The framing is
+/// detected from the first byte of input. Each text may be a Geometry,
+/// Feature or FeatureCollection, and blank records are ignored.
+///
+/// Records are read in chunks of `chunk_size`. Each chunk is parsed and its
+/// bbox computed in parallel while the next chunk is being read, and the
+/// chunk bboxes are merged with `Bbox::merge`. Errors are reported with the
+/// 1-based number of the record they occurred in, which for newline-delimited
+/// input is the line number.
+pub fn sequence_bbox<R: BufRead + Send>(mut reader: R, chunk_size: usize) -> Result<Streamed, ReadError> {
+    // A text sequence starts with a record separator. Consume it so that
+    // every delimited piece of input is then one record.
+    let delimiter = match reader.fill_buf()?.first() {
+        Some(&RECORD_SEPARATOR) => {
+            reader.consume(1);
+            RECORD_SEPARATOR
+        }
+        _ => b'\n',
+    };
+    records_bbox(reader, delimiter, chunk_size, &record_bbox)
+}
+
+
+// Read records split by `delimiter` in chunks of `chunk_size`, computing the
+// bbox of each record with `record_bbox`. Each chunk is computed in parallel
+// while the next one is read.
+pub(crate) fn records_bbox<R, F>(mut reader: R, delimiter: u8, chunk_size: usize, record_bbox: &F)
+    -> Result<Streamed, ReadError>
+    where R: BufRead + Send, F: Fn(&[u8]) -> Result<Streamed, ReadError> + Sync {
+    let chunk_size = chunk_size.max(1);
+    let mut total = Streamed { bbox: Bbox::empty(), skipped: Skipped::default() };
+    let mut number = 0;
+    let mut chunk = read_chunk(&mut reader, delimiter, chunk_size, &mut number)?;
+    while !chunk.is_empty() {
+        let (next, streamed) = rayon::join(
+            || read_chunk(&mut reader, delimiter, chunk_size, &mut number),
+            || chunk_bbox(&chunk, record_bbox));
+        let streamed = streamed?;
+        total.bbox = total.bbox.merge(&streamed.bbox);
+        total.skipped = total.skipped.merge(&streamed.skipped);
+        chunk = next?;
+    }
+    Ok(total)
+}
+
+
+// Read up to chunk_size non-blank records. Returns an empty chunk at the end
+// of input. `number` is the number of the last record read.
+fn read_chunk<R: BufRead>(reader: &mut R, delimiter: u8, chunk_size: usize, number: &mut usize)
+    -> Result<Vec<Record>, ReadError> {
+    let mut chunk = Vec::with_capacity(chunk_size);
+    let mut buf = Vec::new();
+    while chunk.len() < chunk_size {
+        if reader.read_until(delimiter, &mut buf)? == 0 {
+            break;
+        }
+        if buf.last() == Some(&delimiter) {
+            buf.pop();
+        }
+        *number += 1;
+        if buf.iter().any(|b| !b.is_ascii_whitespace()) {
+            chunk.push((*number, mem::take(&mut buf)));
+        }
+        buf.clear();
+    }
+    Ok(chunk)
+}
+
+
+fn chunk_bbox<F>(chunk: &[Record], record_bbox: &F) -> Result<Streamed, ReadError>
+    where F: Fn(&[u8]) -> Result<Streamed, ReadError> + Sync {
+    let results: Vec<Result<Streamed, ReadError>> = chunk.par_iter()
+        .map(|&(number, ref text)| {
+            record_bbox(text).map_err(|e| ReadError::Record(number, Box::new(e)))
+        })
+        .collect();
+
+    let mut total = Streamed { bbox: Bbox::empty(), skipped: Skipped::default() };
+    for result in results {
+        let streamed = result?;
+        total.bbox = total.bbox.merge(&streamed.bbox);
+        total.skipped = total.skipped.merge(&streamed.skipped);
+    }
+    Ok(total)
+}
+
+
+fn record_bbox(text: &[u8]) -> Result<Streamed, ReadError> {
+    let geojson: GeoJson = serde_json::from_slice(text).map_err(|e| ReadError::from_json(text, e))?;
+    Ok(Streamed { bbox: geojson.to_bbox()?, skipped: Skipped::count(&geojson) })
+}
@@ -0,0 +1,427 @@
+use std::convert::TryInto;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::str;
+
+use rayon::prelude::*;
+use serde_json::{Map, Value as JsonValue};
+
+use geojson::{Feature, FeatureCollection, Geometry, Position, Value};
+
+use error::ReadError;
+
+
+const FILE_CODE: i32 = 9994;
+const HEADER_LEN: usize = 100;
+const RECORD_HEADER_LEN: usize = 8;
+
+// Shape types, from the ESRI Shapefile Technical Description.
+const NULL_SHAPE: i32 = 0;
+const POINT: i32 = 1;
+const POLYLINE: i32 = 3;
+const POLYGON: i32 = 5;
+const MULTIPOINT: i32 = 8;
+const POINT_Z: i32 = 11;
+const POLYLINE_Z: i32 = 13;
+const POLYGON_Z: i32 = 15;
+const MULTIPOINT_Z: i32 = 18;
+const POINT_M: i32 = 21;
+const POLYLINE_M: i32 = 23;
+const POLYGON_M: i32 = 25;
+const MULTIPOINT_M: i32 = 28;
+const MULTIPATCH: i32 = 31;
+
+
+/// Read a Shapefile, along with its `.shx` index and `.dbf` attributes if
+/// they are next to it. See `parse_shapefile`.
+pub fn read_shapefile<P: AsRef<Path>>(path: P) -> Result<FeatureCollection, ReadError> {
+    let path = path.as_ref();
+    let shp = fs::read(path)?;
+    let shx = read_sibling(path, "shx")?;
+    let dbf = read_sibling(path, "dbf")?;
+    parse_shapefile(&shp, shx.as_ref().map(|d| &d[..]), dbf.as_ref().map(|d| &d[..]))
+}
+
+
+/// Parse the contents of a `.shp` file into a FeatureCollection with one
+/// Feature per record, in parallel. Records are located with the `.shx`
+/// index if given, or by walking the `.shp` otherwise, and the `.dbf`
+/// attributes, if given, become the features' properties.
+///
+/// Polygon rings are grouped into polygons the way the format defines
+/// them: each clockwise ring is an exterior ring, followed by the
+/// counter-clockwise holes inside it. Records with several exterior rings
+/// become MultiPolygons, so that only exterior rings count towards the
+/// bbox. Null shapes become features with a null geometry.
+///
+/// The extent declared in the `.shp` header is kept as the
+/// FeatureCollection's "bbox" member, and that of each record as its
+/// Feature's, so that `check_bbox_members` compares them with the computed
+/// extents. The z range is included for shape types with z values.
+pub fn parse_shapefile(shp: &[u8], shx: Option<&[u8]>, dbf: Option<&[u8]>) -> Result<FeatureCollection, ReadError> {
+    if shp.len() < HEADER_LEN || be_i32(shp, 0)? != FILE_CODE {
+        return Err(invalid("not a Shapefile"));
+    }
+    let shape_type = le_i32(shp, 32)?;
+    let header = (0..8).map(|i| le_f64(shp, 36 + 8 * i)).collect::<Result<Vec<f64>, ReadError>>()?;
+    let bbox = if has_z(shape_type) {
+        vec![header[0], header[1], header[4], header[2], header[3], header[5]]
+    } else {
+        header[..4].to_vec()
+    };
+
+    let records = match shx {
+        Some(shx) => index_records(shx)?,
+        None => walk_records(shp)?,
+    };
+    let mut properties = match dbf {
+        Some(dbf) => parse_dbf(dbf)?,
+        None => Vec::new(),
+    };
+    if dbf.is_some() && properties.len() != records.len() {
+        return Err(invalid(&format!("the .dbf has {} record(s) but the .shp has {}",
+                                    properties.len(), records.len())));
+    }
+
+    let results: Vec<Result<Feature, ReadError>> = records.par_iter()
+        .enumerate()
+        .map(|(index, &(offset, len))| {
+            record_content(shp, offset, len)
+                .and_then(parse_record)
+                .map_err(|e| ReadError::Record(index + 1, Box::new(e)))
+        })
+        .collect();
+
+    let mut features = Vec::with_capacity(results.len());
+    for (index, result) in results.into_iter().enumerate() {
+        let mut feature = result?;
+        if !properties.is_empty() {
+            feature.properties = Some(mem::take(&mut properties[index]));
+        }
+        features.push(feature);
+    }
+    Ok(FeatureCollection { bbox: Some(bbox), features, foreign_members: None })
+}
+
+
+fn invalid(message: &str) -> ReadError {
+    ReadError::Shapefile(message.to_string())
+}
+
+
+// Read the file with the same name as `path` and the given extension, in
+// lower or upper case, if there is one.
+fn read_sibling(path: &Path, extension: &str) -> io::Result<Option<Vec<u8>>> {
+    for ext in &[extension.to_string(), extension.to_uppercase()] {
+        let sibling = path.with_extension(ext);
+        if sibling.is_file() {
+            return fs::read(sibling).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+
+fn bytes(data: &[u8], pos: usize, len: usize) -> Result<&[u8], ReadError> {
+    data.get(pos..pos + len).ok_or_else(|| invalid("unexpected end of data"))
+}
+
+
+fn be_i32(data: &[u8], pos: usize) -> Result<i32, ReadError> {
+    Ok(i32::from_be_bytes(bytes(data, pos, 4)?.try_into().unwrap()))
+}
+
+
+fn le_i32(data: &[u8], pos: usize) -> Result<i32, ReadError> {
+    Ok(i32::from_le_bytes(bytes(data, pos, 4)?.try_into().unwrap()))
+}
+
+
+fn le_u16(data: &[u8], pos: usize) -> Result<usize, ReadError> {
+    Ok(u16::from_le_bytes(bytes(data, pos, 2)?.try_into().unwrap()) as usize)
+}
+
+
+fn le_f64(data: &[u8], pos: usize) -> Result<f64, ReadError> {
+    Ok(f64::from_le_bytes(bytes(data, pos, 8)?.try_into().unwrap()))
+}
+
+
+// A count or offset, which must not be negative.
+fn le_count(data: &[u8], pos: usize) -> Result<usize, ReadError> {
+    let n = le_i32(data, pos)?;
+    if n < 0 {
+        return Err(invalid("negative count"));
+    }
+    Ok(n as usize)
+}
+
+
+fn has_z(shape_type: i32) -> bool {
+    matches!(shape_type, POINT_Z | POLYLINE_Z | POLYGON_Z | MULTIPOINT_Z | MULTIPATCH)
+}
+
+
+// The byte offset and length of every record's content, from the .shx index.
+fn index_records(shx: &[u8]) -> Result<Vec<(usize, usize)>, ReadError> {
+    if shx.len() < HEADER_LEN || be_i32(shx, 0)? != FILE_CODE {
+        return Err(invalid("the .shx is not a Shapefile index"));
+    }
+    shx[HEADER_LEN..].chunks_exact(8)
+        .enumerate()
+        .map(|(index, entry)| {
+            // Both are in 16-bit words, and the offset is of the record header.
+            let (offset, len) = (be_i32(entry, 0)?, be_i32(entry, 4)?);
+            if offset < 0 || len < 0 {
+                return Err(ReadError::Record(index + 1, Box::new(invalid("negative offset in the .shx"))));
+            }
+            Ok((offset as usize * 2 + RECORD_HEADER_LEN, len as usize * 2))
+        })
+        .collect()
+}
+
+
+// The byte offset and length of every record's content, found by following
+// the record headers through the .shp.
+fn walk_records(shp: &[u8]) -> Result<Vec<(usize, usize)>, ReadError> {
+    let mut records = Vec::new();
+    let mut pos = HEADER_LEN;
+    while pos + RECORD_HEADER_LEN <= shp.len() {
+        let len = be_i32(shp, pos + 4)?;
+        if len < 0 {
+            return Err(ReadError::Record(records.len() + 1, Box::new(invalid("negative content length"))));
+        }
+        records.push((pos + RECORD_HEADER_LEN, len as usize * 2));
+        pos += RECORD_HEADER_LEN + len as usize * 2;
+    }
+    Ok(records)
+}
+
+
+fn record_content(shp: &[u8], offset: usize, len: usize) -> Result<&[u8], ReadError> {
+    shp.get(offset..offset + len).ok_or_else(|| invalid("record is truncated"))
+}
+
+
+// Parse one record's content into a Feature, with the record's bbox if it
+// has one.
+fn parse_record(content: &[u8]) -> Result<Feature, ReadError> {
+    let shape_type = le_i32(content, 0)?;
+    let z = has_z(shape_type);
+    let (value, bbox) = match shape_type {
+        NULL_SHAPE => (None, None),
+        POINT | POINT_Z | POINT_M => {
+            let mut position = vec![le_f64(content, 4)?, le_f64(content, 12)?];
+            if z {
+                position.push(le_f64(content, 20)?);
+            }
+            (Some(Value::Point(position)), None)
+        }
+        MULTIPOINT | MULTIPOINT_Z | MULTIPOINT_M => {
+            let n = le_count(content, 36)?;
+            let points = positions(content, 40, n, z)?;
+            (Some(Value::MultiPoint(points)), Some(record_bbox(content, 40 + 16 * n, z)?))
+        }
+        POLYLINE | POLYLINE_Z | POLYLINE_M | POLYGON | POLYGON_Z | POLYGON_M => {
+            let num_parts = le_count(content, 36)?;
+            let num_points = le_count(content, 40)?;
+            let points_start = 44 + 4 * num_parts;
+            let points = positions(content, points_start, num_points, z)?;
+            let parts = parts(content, 44, num_parts, points)?;
+            let value = if matches!(shape_type, POLYGON | POLYGON_Z | POLYGON_M) {
+                polygons(parts)
+            } else if parts.len() == 1 {
+                Value::LineString(parts.into_iter().next().unwrap())
+            } else {
+                Value::MultiLineString(parts)
+            };
+            (Some(value), Some(record_bbox(content, points_start + 16 * num_points, z)?))
+        }
+        MULTIPATCH => return Err(invalid("MultiPatch shapes are not supported")),
+        _ => return Err(invalid(&format!("unknown shape type {}", shape_type))),
+    };
+    Ok(Feature {
+        bbox,
+        geometry: value.map(Geometry::new),
+        id: None,
+        properties: None,
+        foreign_members: None,
+    })
+}
+
+
+// `n` positions starting at `start`, with their z values if the shape has
+// them. The z values follow all of the x and y values, after the z range.
+fn positions(content: &[u8], start: usize, n: usize, z: bool) -> Result<Vec<Position>, ReadError> {
+    // Check the length up front rather than trusting `n` for the allocation.
+    let z_start = start + 16 * n + 16;
+    bytes(content, start, 16 * n)?;
+    if z {
+        bytes(content, z_start, 8 * n)?;
+    }
+    (0..n)
+        .map(|i| {
+            let mut position = vec![le_f64(content, start + 16 * i)?, le_f64(content, start + 16 * i + 8)?];
+            if z {
+                position.push(le_f64(content, z_start + 8 * i)?);
+            }
+            Ok(position)
+        })
+        .collect()
+}
+
+
+// Split the points into parts at the indices starting at `start`.
+fn parts(content: &[u8], start: usize, num_parts: usize, mut points: Vec<Position>) -> Result<Vec<Vec<Position>>, ReadError> {
+    if num_parts == 0 {
+        return Ok(Vec::new());
+    }
+    let mut starts = (0..num_parts).map(|i| le_count(content, start + 4 * i)).collect::<Result<Vec<usize>, ReadError>>()?;
+    if starts.first() != Some(&0) || starts.windows(2).any(|w| w[0] > w[1]) || starts[num_parts - 1] > points.len() {
+        return Err(invalid("invalid part indices"));
+    }
+    // Split from the back so that each split_off leaves the earlier parts.
+    starts.remove(0);
+    let mut parts = Vec::with_capacity(num_parts);
+    for &start in starts.iter().rev() {
+        parts.push(points.split_off(start));
+    }
+    parts.push(points);
+    parts.reverse();
+    Ok(parts)
+}
+
+
+// The record's bbox, `[xmin, ymin, xmax, ymax]`, which follows the shape
+// type, with the z range after the x and y values of its `z_range_start`.
+fn record_bbox(content: &[u8], z_range_start: usize, z: bool) -> Result<Vec<f64>, ReadError> {
+    let xy = (0..4).map(|i| le_f64(content, 4 + 8 * i)).collect::<Result<Vec<f64>, ReadError>>()?;
+    if z {
+        let (zmin, zmax) = (le_f64(content, z_range_start)?, le_f64(content, z_range_start + 8)?);
+        Ok(vec![xy[0], xy[1], zmin, xy[2], xy[3], zmax])
+    } else {
+        Ok(xy)
+    }
+}
+
+
+// Group the rings of a polygon record into polygons: clockwise rings are
+// exteriors and counter-clockwise rings are holes of the exterior that
+// contains them. A hole that isn't inside any exterior is taken as an
+// exterior of its own, and a record with no clockwise rings at all as wound
+// the wrong way round, all its rings being exteriors, so that wrongly wound
+// data still counts in full.
+fn polygons(rings: Vec<Vec<Position>>) -> Value {
+    let (mut exteriors, mut holes): (Vec<_>, Vec<_>) = rings.into_iter().partition(|ring| signed_area(ring) < 0.0);
+    if exteriors.is_empty() {
+        mem::swap(&mut exteriors, &mut holes);
+    }
+    let mut polygons: Vec<Vec<Vec<Position>>> = exteriors.into_iter().map(|ring| vec![ring]).collect();
+    let num_exteriors = polygons.len();
+    for hole in holes {
+        // A hole may touch its exterior, so any one vertex inside will do.
+        let container = polygons[..num_exteriors].iter()
+            .position(|polygon| hole.iter().any(|p| contains(&polygon[0], p)));
+        match container {
+            Some(i) => polygons[i].push(hole),
+            None => polygons.push(vec![hole]),
+        }
+    }
+    if polygons.len() == 1 {
+        Value::Polygon(polygons.pop().unwrap())
+    } else {
+        Value::MultiPolygon(polygons)
+    }
+}
+
+
+// Twice the signed area of a ring, negative if it is clockwise.
+fn signed_area(ring: &[Position]) -> f64 {
+    ring.windows(2).map(|w| w[0][0] * w[1][1] - w[1][0] * w[0][1]).sum()
+}
+
+
+// Whether the point is inside the ring, by ray casting.
+fn contains(ring: &[Position], point: &Position) -> bool {
+    let (x, y) = (point[0], point[1]);
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let (x0, y0, x1, y1) = (w[0][0], w[0][1], w[1][0], w[1][1]);
+        if (y0 > y) != (y1 > y) && x < x0 + (y - y0) / (y1 - y0) * (x1 - x0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+
+// The attributes of every record in a dBase III table, as GeoJSON
+// properties. Character fields are trimmed and read as UTF-8, or as
+// Latin-1 if they aren't valid UTF-8. Blank numbers and unknown logicals
+// are null.
+fn parse_dbf(dbf: &[u8]) -> Result<Vec<Map<String, JsonValue>>, ReadError> {
+    let num_records = le_count(dbf, 4)?;
+    let header_len = le_u16(dbf, 8)?;
+    let record_len = le_u16(dbf, 10)?;
+
+    // (name, type, offset within the record, length)
+    let mut fields = Vec::new();
+    let mut pos = 32;
+    let mut offset = 1;
+    while pos + 32 <= header_len && bytes(dbf, pos, 1)?[0] != 0x0d {
+        let descriptor = bytes(dbf, pos, 32)?;
+        let name_len = descriptor[..11].iter().position(|&b| b == 0).unwrap_or(11);
+        let name = text(&descriptor[..name_len]);
+        let len = descriptor[16] as usize;
+        fields.push((name, descriptor[11], offset, len));
+        offset += len;
+        pos += 32;
+    }
+    if offset > record_len {
+        return Err(invalid("the .dbf fields are longer than its records"));
+    }
+    bytes(dbf, header_len, num_records * record_len)
+        .map_err(|_| invalid("the .dbf is truncated"))?;
+
+    Ok((0..num_records).into_par_iter()
+        .map(|i| {
+            let record = &dbf[header_len + i * record_len..header_len + (i + 1) * record_len];
+            fields.iter()
+                .map(|&(ref name, kind, offset, len)| (name.clone(), dbf_value(kind, &record[offset..offset + len])))
+                .collect()
+        })
+        .collect())
+}
+
+
+fn dbf_value(kind: u8, raw: &[u8]) -> JsonValue {
+    let value = text(raw);
+    let value = value.trim();
+    match kind {
+        b'N' | b'F' | b'O' => {
+            value.parse::<f64>().ok()
+                .and_then(|n| {
+                    // Keep integers as integers, as they are usually ids.
+                    if n.fract() == 0.0 && n.abs() < 1e15 { Some(JsonValue::from(n as i64)) }
+                    else { serde_json::Number::from_f64(n).map(JsonValue::Number) }
+                })
+                .unwrap_or(JsonValue::Null)
+        }
+        b'L' => match value {
+            "T" | "t" | "Y" | "y" => JsonValue::Bool(true),
+            "F" | "f" | "N" | "n" => JsonValue::Bool(false),
+            _ => JsonValue::Null,
+        },
+        _ => JsonValue::String(value.to_string()),
+    }
+}
+
+
+fn text(raw: &[u8]) -> String {
+    match str::from_utf8(raw) {
+        Ok(s) => s.to_string(),
+        Err(_) => raw.iter().map(|&b| b as char).collect(),
+    }
+}
@@ -0,0 +1,95 @@
+use rayon::prelude::*;
+
+use geojson::{Feature, GeoJson, Geometry, Value};
+
+
+/// Why a feature contributed nothing to the total bounding box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The Feature's "geometry" member is null.
+    NullGeometry,
+    /// The geometry has no coordinates, e.g. an empty GeometryCollection or a
+    /// MultiPolygon with no polygons.
+    EmptyGeometry,
+}
+
+
+impl SkipReason {
+    pub fn of(feature: &Feature) -> Option<SkipReason> {
+        match feature.geometry {
+            None => Some(SkipReason::NullGeometry),
+            Some(ref geometry) if is_empty(geometry) => Some(SkipReason::EmptyGeometry),
+            Some(_) => None,
+        }
+    }
+}
+
+
+/// Counts of features that contributed nothing to a bounding box, by reason.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Skipped {
+    pub null_geometry: usize,
+    pub empty_geometry: usize,
+}
+
+
+impl Skipped {
+    /// Count the skipped features in a GeoJSON object. Only Features can be
+    /// skipped; a bare Geometry never counts even if it is empty.
+    pub fn count(geojson: &GeoJson) -> Skipped {
+        match *geojson {
+            GeoJson::Geometry(_) => Skipped::default(),
+            GeoJson::Feature(ref feature) => Skipped::of(feature),
+            GeoJson::FeatureCollection(ref fc) => Skipped::of_features(&fc.features),
+        }
+    }
+
+    /// Count the skipped features in a slice of features.
+    pub fn of_features(features: &[Feature]) -> Skipped {
+        features.par_iter()
+            .map(Skipped::of)
+            .reduce(Skipped::default, |a, b| a.merge(&b))
+    }
+
+    fn of(feature: &Feature) -> Skipped {
+        let mut skipped = Skipped::default();
+        match SkipReason::of(feature) {
+            Some(SkipReason::NullGeometry) => skipped.null_geometry += 1,
+            Some(SkipReason::EmptyGeometry) => skipped.empty_geometry += 1,
+            None => (),
+        }
+        skipped
+    }
+
+    pub fn merge(&self, other: &Skipped) -> Skipped {
+        Skipped {
+            null_geometry: self.null_geometry + other.null_geometry,
+            empty_geometry: self.empty_geometry + other.empty_geometry,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.null_geometry + self.empty_geometry
+    }
+}
+
+
+// A geometry is empty if it has no positions that would contribute to its
+// bounding box. This follows the same rules as ToBbox for Value, so only the
+// outer ring of a polygon is considered. It only walks the arrays, never the
+// positions themselves.
+fn is_empty(geometry: &Geometry) -> bool {
+    match geometry.value {
+        Value::Point(_) => false,
+        Value::MultiPoint(ref vp) | Value::LineString(ref vp) => vp.is_empty(),
+        Value::MultiLineString(ref vvp) => vvp.iter().all(|vp| vp.is_empty()),
+        Value::Polygon(ref vvp) => polygon_is_empty(vvp),
+        Value::MultiPolygon(ref vvvp) => vvvp.iter().all(|vvp| polygon_is_empty(vvp)),
+        Value::GeometryCollection(ref geoms) => geoms.iter().all(is_empty),
+    }
+}
+
+
+fn polygon_is_empty<T>(vvp: &[Vec<T>]) -> bool {
+    vvp.first().is_none_or(|ring| ring.is_empty())
+}
@@ -0,0 +1,153 @@
+use std::ops::Range;
+
+
+// Finds the elements of a FeatureCollection's "features" array by following
+// the structure of the JSON text: strings, so that brackets inside them are
+// ignored, and the nesting of brackets. Nothing else is checked, so a
+// malformed text can give wrong boundaries. Every piece is parsed properly
+// afterwards, and if they are all valid, so is the whole text.
+
+
+// The byte range of the top-level "features" array and those of each of its
+// elements, or None if there is no such array or the text is malformed.
+pub(crate) fn features_array(data: &[u8]) -> Option<(Range<usize>, Vec<Range<usize>>)> {
+    let mut pos = skip_whitespace(data, 0);
+    if data.get(pos) != Some(&b'{') {
+        return None;
+    }
+    pos = skip_whitespace(data, pos + 1);
+    loop {
+        if data.get(pos) != Some(&b'"') {
+            return None;
+        }
+        let key_end = string_end(data, pos)?;
+        let key = &data[pos + 1..key_end - 1];
+        pos = skip_whitespace(data, key_end);
+        if data.get(pos) != Some(&b':') {
+            return None;
+        }
+        pos = skip_whitespace(data, pos + 1);
+        let end = value_end(data, pos)?;
+        if key == b"features" && data[pos] == b'[' {
+            return Some((pos..end, elements(data, pos..end)?));
+        }
+        pos = skip_whitespace(data, end);
+        match data.get(pos) {
+            Some(&b',') => pos = skip_whitespace(data, pos + 1),
+            _ => return None,
+        }
+    }
+}
+
+
+// Group consecutive elements into chunks of at least `chunk_bytes` bytes
+// (except perhaps the last). Each chunk comes with the index of its first
+// element.
+pub(crate) fn chunks(elements: &[Range<usize>], chunk_bytes: usize) -> Vec<(usize, &[Range<usize>])> {
+    let mut chunks = Vec::new();
+    let mut first = 0;
+    let mut bytes = 0;
+    for (i, element) in elements.iter().enumerate() {
+        bytes += element.len();
+        if bytes >= chunk_bytes {
+            chunks.push((first, &elements[first..i + 1]));
+            first = i + 1;
+            bytes = 0;
+        }
+    }
+    if first < elements.len() {
+        chunks.push((first, &elements[first..]));
+    }
+    chunks
+}
+
+
+// The ranges of the elements of the array at `array`.
+fn elements(data: &[u8], array: Range<usize>) -> Option<Vec<Range<usize>>> {
+    let mut ranges = Vec::new();
+    let mut pos = skip_whitespace(data, array.start + 1);
+    if pos + 1 == array.end {
+        return Some(ranges);
+    }
+    loop {
+        let end = value_end(data, pos)?;
+        ranges.push(pos..end);
+        pos = skip_whitespace(data, end);
+        match data.get(pos) {
+            Some(&b',') => pos = skip_whitespace(data, pos + 1),
+            Some(&b']') if pos + 1 == array.end => return Some(ranges),
+            _ => return None,
+        }
+    }
+}
+
+
+fn skip_whitespace(data: &[u8], mut pos: usize) -> usize {
+    while pos < data.len() && matches!(data[pos], b' ' | b'\t' | b'\n' | b'\r') {
+        pos += 1;
+    }
+    pos
+}
+
+
+// The end of the string whose opening quote is at `pos`, just past the
+// closing quote.
+fn string_end(data: &[u8], pos: usize) -> Option<usize> {
+    let mut i = pos + 1;
+    loop {
+        // A backslash at the very end leaves nothing to search.
+        i += data.get(i..)?.iter().position(|&b| b == b'"' || b == b'\\')?;
+        if data[i] == b'"' {
+            return Some(i + 1);
+        }
+        i += 2;
+    }
+}
+
+
+// The end of the value starting at `pos`.
+fn value_end(data: &[u8], pos: usize) -> Option<usize> {
+    match *data.get(pos)? {
+        b'"' => string_end(data, pos),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut i = pos;
+            loop {
+                // Jump to the next quote or bracket.
+                i += data[i..].iter().position(|&b| STRUCTURAL[b as usize])?;
+                match data[i] {
+                    b'"' => {
+                        i = string_end(data, i)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    _ => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(i + 1);
+                        }
+                    }
+                }
+                i += 1;
+            }
+        }
+        _ => {
+            let len = data[pos..].iter()
+                .position(|&b| matches!(b, b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r'))
+                .unwrap_or(data.len() - pos);
+            Some(pos + len)
+        }
+    }
+}
+
+
+// The bytes value_end has to stop at: quotes and brackets.
+const STRUCTURAL: [bool; 256] = {
+    let mut table = [false; 256];
+    table[b'"' as usize] = true;
+    table[b'{' as usize] = true;
+    table[b'}' as usize] = true;
+    table[b'[' as usize] = true;
+    table[b']' as usize] = true;
+    table
+};
@@ -0,0 +1,31 @@
+/// The spread of one phase's timings over the repeats of a benchmark, in
+/// seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhaseStats {
+    pub min: f64,
+    pub median: f64,
+    /// The 95th percentile, by the nearest-rank method.
+    pub p95: f64,
+    pub max: f64,
+}
+
+
+impl PhaseStats {
+    /// Summarize some timings, or None if there are none. The median of an
+    /// even number of timings is the mean of the middle two.
+    pub fn new(samples: &[f64]) -> Option<PhaseStats> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut samples = samples.to_vec();
+        samples.sort_by(|a, b| a.total_cmp(b));
+        let n = samples.len();
+        let median = if n % 2 == 1 { samples[n / 2] } else { (samples[n / 2 - 1] + samples[n / 2]) / 2.0 };
+        Some(PhaseStats {
+            min: samples[0],
+            median,
+            p95: samples[(n * 95).div_ceil(100) - 1],
+            max: samples[n - 1],
+        })
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt;
+use std::io::{self, Read};
+use std::str;
+use std::sync::mpsc::{self, SyncSender};
+use std::thread;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{self, Map, Value as JsonValue};
+
+use geojson::{Feature, GeoJson};
+
+use bbox::Bbox;
+use error::{BboxError, ReadError};
+use skipped::Skipped;
+use to_bbox::{BboxOptions, ToBbox, compute_weighted_bbox_from, feature_vertices};
+
+
+/// The number of features `stream_bbox` parses before handing them off.
+pub const DEFAULT_BATCH_SIZE: usize = 4096;
+
+
+/// The result of computing a bounding box from a stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Streamed {
+    pub bbox: Bbox,
+    pub skipped: Skipped,
+}
+
+
+/// Compute the bounding box of a GeoJSON document without holding all of
+/// it in memory.
+///
+/// The "features" array of a FeatureCollection is parsed incrementally, in
+/// batches of `batch_size` features. While the next batch is being parsed,
+/// the previous one is handed to a worker thread that computes its bbox in
+/// parallel with `compute_bbox` and merges it into the running total. At
+/// most three batches are alive at once: one being parsed, one waiting and
+/// one being computed.
+///
+/// The other members of the document are small and are kept. A document
+/// without a "features" array (a Feature or a Geometry) is rebuilt from them
+/// and handled as usual, so the result is the same as parsing the whole
+/// document and calling `to_bbox`.
+pub fn stream_bbox<R: Read>(reader: R, batch_size: usize) -> Result<Streamed, ReadError> {
+    let batch_size = batch_size.max(1);
+    let (sender, receiver) = mpsc::sync_channel::<Vec<Feature>>(1);
+
+    let worker = thread::spawn(move || -> Result<Streamed, BboxError> {
+        let mut streamed = Streamed { bbox: Bbox::empty(), skipped: Skipped::default() };
+        let mut offset = 0;
+        let options = BboxOptions::default();
+        for batch in receiver {
+            let bbox = compute_weighted_bbox_from(&batch, offset, &options, &feature_vertices,
+                                                  &|f: &Feature| f.to_bbox_with(&options))
+                .map_err(|e| e.in_field("features"))?;
+            streamed.bbox = streamed.bbox.merge(&bbox);
+            streamed.skipped = streamed.skipped.merge(&Skipped::of_features(&batch));
+            offset += batch.len();
+        }
+        Ok(streamed)
+    });
+
+    let mut checked = Utf8Reader::new(reader);
+    let parsed = {
+        let mut deserializer = serde_json::Deserializer::from_reader(&mut checked);
+        DocumentSeed { sender, batch_size }
+            .deserialize(&mut deserializer)
+            .and_then(|members| deserializer.end().map(|_| members))
+    };
+
+    // The sender has been dropped, so the worker finishes up once it has
+    // computed whatever was sent. A bbox error takes precedence over the
+    // parse error it caused by hanging up on the parser.
+    let streamed = worker.join().expect("bbox worker panicked")?;
+    match parsed.map_err(|e| checked.error(e))? {
+        None => Ok(streamed),
+        Some(members) => {
+            let geojson = GeoJson::deserialize(JsonValue::Object(members))?;
+            Ok(Streamed { bbox: geojson.to_bbox()?, skipped: Skipped::count(&geojson) })
+        }
+    }
+}
+
+
+// Passes reads through, noting what `ReadError::from_json` would make of the
+// input read so far: where its first invalid UTF-8 sequence starts and
+// whether it is only whitespace.
+struct Utf8Reader<R> {
+    inner: R,
+    // The number of bytes checked to be valid UTF-8.
+    valid: usize,
+    // The start of a sequence cut off at the end of the last read.
+    partial: Vec<u8>,
+    invalid: bool,
+    blank: bool,
+    ended: bool,
+}
+
+
+impl<R: Read> Utf8Reader<R> {
+    fn new(inner: R) -> Self {
+        Utf8Reader { inner, valid: 0, partial: Vec::new(), invalid: false, blank: true, ended: false }
+    }
+
+    fn check(&mut self, mut data: &[u8]) {
+        self.blank = self.blank && data.iter().all(u8::is_ascii_whitespace);
+        if self.invalid {
+            return;
+        }
+        // Finish the sequence the last read cut off, one byte at a time.
+        while !self.partial.is_empty() {
+            let (&byte, rest) = match data.split_first() {
+                Some(split) => split,
+                None => return,
+            };
+            self.partial.push(byte);
+            data = rest;
+            match str::from_utf8(&self.partial) {
+                Ok(_) => {
+                    self.valid += self.partial.len();
+                    self.partial.clear();
+                }
+                Err(e) if e.error_len().is_some() => {
+                    self.invalid = true;
+                    return;
+                }
+                Err(_) => (),
+            }
+        }
+        match str::from_utf8(data) {
+            Ok(_) => self.valid += data.len(),
+            Err(e) => {
+                self.valid += e.valid_up_to();
+                match e.error_len() {
+                    Some(_) => self.invalid = true,
+                    None => self.partial = data[e.valid_up_to()..].to_vec(),
+                }
+            }
+        }
+    }
+
+    // The ReadError for a parse error in the input read so far. A sequence
+    // cut off by the end of the input is invalid.
+    fn error(&self, e: serde_json::Error) -> ReadError {
+        let cut_off = self.ended && !self.partial.is_empty();
+        let not_utf8 = if self.invalid || cut_off { Some(self.valid) } else { None };
+        ReadError::json_error(e, not_utf8, self.blank)
+    }
+}
+
+
+impl<R: Read> Read for Utf8Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.ended = self.ended || (n == 0 && !buf.is_empty());
+        self.check(&buf[..n]);
+        Ok(n)
+    }
+}
+
+
+// Deserializes the top-level object. Features are sent off in batches and
+// every other member is collected. Returns the other members only if there
+// was no "features" array.
+struct DocumentSeed {
+    sender: SyncSender<Vec<Feature>>,
+    batch_size: usize,
+}
+
+
+impl<'de> DeserializeSeed<'de> for DocumentSeed {
+    type Value = Option<Map<String, JsonValue>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+
+impl<'de> Visitor<'de> for DocumentSeed {
+    type Value = Option<Map<String, JsonValue>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a GeoJSON object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut members = Map::new();
+        let mut saw_features = false;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "features" {
+                map.next_value_seed(FeaturesSeed { sender: &self.sender, batch_size: self.batch_size })?;
+                saw_features = true;
+            } else {
+                members.insert(key, map.next_value()?);
+            }
+        }
+        Ok(if saw_features { None } else { Some(members) })
+    }
+}
+
+
+// Deserializes the "features" array one Feature at a time.
+struct FeaturesSeed<'a> {
+    sender: &'a SyncSender<Vec<Feature>>,
+    batch_size: usize,
+}
+
+
+impl<'de, 'a> DeserializeSeed<'de> for FeaturesSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+
+impl<'de, 'a> Visitor<'de> for FeaturesSeed<'a> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of GeoJSON Features")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut batch = Vec::with_capacity(self.batch_size);
+        while let Some(feature) = seq.next_element::<Feature>()? {
+            batch.push(feature);
+            if batch.len() == self.batch_size {
+                let full = std::mem::replace(&mut batch, Vec::with_capacity(self.batch_size));
+                self.sender.send(full).map_err(|_| de::Error::custom("bbox computation stopped"))?;
+            }
+        }
+        if !batch.is_empty() {
+            self.sender.send(batch).map_err(|_| de::Error::custom("bbox computation stopped"))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,447 @@
+use std::error::Error;
+
+use rayon::{self, ThreadPool};
+use rayon::prelude::*;
+
+use geojson::{GeoJson, Feature, FeatureCollection, Geometry, Position, Value};
+
+use bbox::Bbox;
+use error::{BboxError, BboxErrorKind};
+
+
+/// The number of elements below which `GrainSize::Adaptive` stops splitting
+/// and folds a slice sequentially.
+pub const MIN_GRAIN_SIZE: usize = 256;
+
+// How many pieces per thread `GrainSize::Adaptive` aims to split a slice
+// into, so that idle threads have something to steal.
+const PIECES_PER_THREAD: usize = 4;
+
+
+/// How finely `compute_bbox` splits a slice before switching to a plain
+/// sequential loop over its elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrainSize {
+    /// Split each slice into a few pieces per rayon thread, but never into
+    /// pieces smaller than `MIN_GRAIN_SIZE` elements.
+    Adaptive,
+    /// Fold slices of at most this many elements sequentially. `Fixed(1)`
+    /// splits all the way down to single elements.
+    Fixed(usize),
+}
+
+
+impl GrainSize {
+    // The largest slice to fold sequentially, for a slice of `len` elements.
+    fn for_len(&self, len: usize) -> usize {
+        match *self {
+            GrainSize::Adaptive => {
+                let pieces = rayon::current_num_threads() * PIECES_PER_THREAD;
+                (len / pieces).max(MIN_GRAIN_SIZE)
+            }
+            GrainSize::Fixed(n) => n.max(1),
+        }
+    }
+}
+
+
+/// How slices of features and geometries are split in two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    /// Halve the number of elements.
+    Elements,
+    /// Halve the number of vertices, so that a few huge polygons among many
+    /// small features still give balanced halves. Grain sizes then count
+    /// vertices rather than elements.
+    Vertices,
+}
+
+
+/// How the bbox of an array is computed from the bboxes of its elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Recursively halve the array with `rayon::join` down to the grain
+    /// size.
+    Join,
+    /// `par_iter().map().reduce()`, leaving the splitting to rayon. Pieces
+    /// are at least the grain size, counted in elements since rayon only
+    /// splits by index.
+    Reduce,
+    /// A plain loop on the current thread, for when the caller already
+    /// controls threading.
+    Sequential,
+}
+
+
+impl Strategy {
+    /// Look up a strategy by name: join, reduce or sequential.
+    pub fn from_name(name: &str) -> Option<Strategy> {
+        match name {
+            "join" => Some(Strategy::Join),
+            "reduce" => Some(Strategy::Reduce),
+            "sequential" => Some(Strategy::Sequential),
+            _ => None,
+        }
+    }
+}
+
+
+/// Build a rayon thread pool with `num_threads` threads for use with
+/// `ToBbox::to_bbox_in`.
+pub fn thread_pool(num_threads: usize) -> Result<ThreadPool, Box<dyn Error>> {
+    // rayon 0.8 calls the builder Configuration, a name that newer
+    // rayon-core releases deprecate.
+    #[allow(deprecated)]
+    ThreadPool::new(rayon::Configuration::new().num_threads(num_threads))
+}
+
+
+/// Settings for how a bounding box is computed. The result is the same
+/// whatever the settings; only the way the work is split up changes.
+///
+/// Start from `BboxOptions::default()` and change what you need with the
+/// `with_` methods, e.g.
+/// `BboxOptions::default().with_grain_size(GrainSize::Fixed(1))`, so that
+/// new settings don't break existing code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BboxOptions {
+    pub strategy: Strategy,
+    pub grain_size: GrainSize,
+    /// Only used by Strategy::Join.
+    pub split_by: SplitBy,
+}
+
+
+impl Default for BboxOptions {
+    fn default() -> Self {
+        BboxOptions { strategy: Strategy::Join, grain_size: GrainSize::Adaptive, split_by: SplitBy::Vertices }
+    }
+}
+
+
+impl BboxOptions {
+    pub fn with_strategy(self, strategy: Strategy) -> Self {
+        BboxOptions { strategy, ..self }
+    }
+
+    pub fn with_grain_size(self, grain_size: GrainSize) -> Self {
+        BboxOptions { grain_size, ..self }
+    }
+
+    pub fn with_split_by(self, split_by: SplitBy) -> Self {
+        BboxOptions { split_by, ..self }
+    }
+}
+
+
+/// Computes the bounding box of a GeoJSON object.
+pub trait ToBbox {
+    fn to_bbox(&self) -> Result<Bbox, BboxError> {
+        self.to_bbox_with(&BboxOptions::default())
+    }
+
+    fn to_bbox_with(&self, options: &BboxOptions) -> Result<Bbox, BboxError>;
+
+    /// Compute the bbox on `pool` rather than rayon's global pool, e.g. to
+    /// keep it apart from other rayon work. The pool must come from the
+    /// same version of rayon as this crate; `thread_pool` builds one.
+    fn to_bbox_in(&self, pool: &ThreadPool, options: &BboxOptions) -> Result<Bbox, BboxError>
+        where Self: Sync {
+        pool.install(|| self.to_bbox_with(options))
+    }
+}
+
+
+impl ToBbox for Position {
+    // A GeoJson::Position is a (longitude, latitude) tuple, optionally
+    // followed by an elevation. The min/max of the bounding box are the
+    // longitude, latitude (and elevation) of the Position. Any further
+    // elements are ignored.
+    fn to_bbox_with(&self, _: &BboxOptions) -> Result<Bbox, BboxError> {
+        match self.len() {
+            0 | 1 => Err(BboxError::new(BboxErrorKind::ShortPosition(self.len()))),
+            2 => Ok(Bbox::from_point(self[0], self[1])),
+            _ => Ok(Bbox::from_point_3d(self[0], self[1], self[2])),
+        }
+    }
+}
+
+
+impl ToBbox for Geometry {
+    fn to_bbox_with(&self, options: &BboxOptions) -> Result<Bbox, BboxError> {
+        self.value.to_bbox_with(options)
+    }
+}
+
+
+impl ToBbox for Feature {
+    // A Feature's bounding box is the bounding box of its geometry. The
+    // geometry is optional; a Feature without one has an empty bounding box.
+    fn to_bbox_with(&self, options: &BboxOptions) -> Result<Bbox, BboxError> {
+        match self.geometry {
+            Some(ref geometry) => geometry.to_bbox_with(options).map_err(|e| e.in_field("geometry")),
+            None => Ok(Bbox::empty()),
+        }
+    }
+}
+
+
+impl ToBbox for FeatureCollection {
+    // Recursively split up the feature collection's bounding box into the
+    // bounding box of the individual features.
+    fn to_bbox_with(&self, options: &BboxOptions) -> Result<Bbox, BboxError> {
+        compute_bbox_weighted(&self.features, options, &feature_vertices, &|f| f.to_bbox_with(options))
+            .map_err(|e| e.in_field("features"))
+    }
+}
+
+
+impl ToBbox for GeoJson {
+    fn to_bbox_with(&self, options: &BboxOptions) -> Result<Bbox, BboxError> {
+        match *self {
+            GeoJson::Geometry(ref geometry) => geometry.to_bbox_with(options),
+            GeoJson::Feature(ref feature) => feature.to_bbox_with(options),
+            GeoJson::FeatureCollection(ref fc) => fc.to_bbox_with(options),
+        }
+    }
+}
+
+
+// This is a helper function that we use a bunch below in the bounding box
+// calculation of each geometry type.
+fn position_bbox(p: &Position) -> Result<Bbox, BboxError> { p.to_bbox() }
+
+
+// The number of positions the bbox of a geometry looks at. Polygons only
+// count their outer ring since that is all that is used.
+fn vertices(value: &Value) -> usize {
+    match *value {
+        Value::Point(_) => 1,
+        Value::MultiPoint(ref vp) | Value::LineString(ref vp) => vp.len(),
+        Value::MultiLineString(ref vvp) => vvp.iter().map(|vp| vp.len()).sum(),
+        Value::Polygon(ref vvp) => polygon_vertices(vvp),
+        Value::MultiPolygon(ref vvvp) => vvvp.iter().map(|vvp| polygon_vertices(vvp)).sum(),
+        Value::GeometryCollection(ref geoms) => geoms.iter().map(geometry_vertices).sum(),
+    }
+}
+
+
+fn polygon_vertices(vvp: &[Vec<Position>]) -> usize {
+    vvp.first().map_or(0, |ring| ring.len())
+}
+
+
+fn geometry_vertices(geometry: &Geometry) -> usize {
+    vertices(&geometry.value)
+}
+
+
+pub(crate) fn feature_vertices(feature: &Feature) -> usize {
+    feature.geometry.as_ref().map_or(0, geometry_vertices)
+}
+
+
+// The bounding box of a polygon is the bounding box of its first, outer
+// ring. Errors in the ring are reported against index 0 of the polygon. A
+// polygon with no rings at all is empty.
+fn polygon_bbox(vvp: &[Vec<Position>], options: &BboxOptions) -> Result<Bbox, BboxError> {
+    match vvp.first() {
+        Some(ring) => compute_bbox_with(ring, options, &position_bbox).map_err(|e| e.at_index(0)),
+        None => Ok(Bbox::empty()),
+    }
+}
+
+
+impl ToBbox for Value {
+    fn to_bbox_with(&self, options: &BboxOptions) -> Result<Bbox, BboxError> {
+        let coordinates = match *self {
+            // Point is GeoJson::Position or Vec<f64> which is
+            // a [longitude,latitude] pair
+            Value::Point(ref p) => p.to_bbox(),
+
+            // MultiPoint is Vec<Position>
+            // Break up the MultiPoint into smaller MultiPoints until we get
+            // to a single Position value, then use position_bbox to return
+            // the single position's value and combine back up the chain.
+            Value::MultiPoint(ref vp) => compute_bbox_with(vp, options, &position_bbox),
+
+            // LineString is Vec<Position>
+            Value::LineString(ref vp) => compute_bbox_with(vp, options, &position_bbox),
+
+            // MultiLineString is Vec<Vec<Position>>
+            Value::MultiLineString(ref vvp) => {
+                compute_bbox_weighted(vvp, options, &|vp| vp.len(),
+                                      &|vp| compute_bbox_with(vp, options, &position_bbox))
+            }
+
+            // Polygon is Vec<Vec<Position>>. The first element is the outer
+            // ring / exterior of the polygon which we use to compute the
+            // bounding box of the total polygon.  Extract the first element
+            // (which is like a LineString) and return its bounding box.
+            Value::Polygon(ref vvp) => polygon_bbox(vvp, options),
+
+            // MultiPolygon is Vec<Vec<Vec<Position>>>, a Vec of polygon
+            // coordinates. When we get to an individual polygon, just use its
+            // outer ring like the Polygon code above.
+            Value::MultiPolygon(ref vvvp) => {
+                compute_bbox_weighted(vvvp, options, &|vvp| polygon_vertices(vvp),
+                                      &|vvp| polygon_bbox(vvp, options))
+            }
+
+            // GeometryCollection is Vec<Geometry>. It has no coordinates of
+            // its own so errors point into its "geometries" member instead.
+            Value::GeometryCollection(ref geoms) => {
+                return compute_bbox_weighted(geoms, options, &geometry_vertices, &|g| g.to_bbox_with(options))
+                    .map_err(|e| e.in_field("geometries"));
+            }
+        };
+        coordinates.map_err(|e| e.in_field("coordinates"))
+    }
+}
+
+
+/// Divide and conquer approach for computing bounding boxes.  This relies on
+/// the fact that the bounding box of an array of objects is the merged
+/// bounding box of the first half of the array with the bounding box of the
+/// second half of the array. We recursively split up the array until we
+/// compute the bounding box of a single element, and the combining the
+/// bounding boxes to compute the overall bounding box. Computing the bounding
+/// box of the individual elements are broken down the same way until we reach
+/// a single coordinate (Position) pair.  The final process may have varying
+/// levels of nesting depending on the structure of the data.  `func` is
+/// supplied to compute the bounding box of a single value. We use different
+/// behavior for the same type (such as Vec<Vec<Position>>) depending on the
+/// geometry type (i.e., Polygon vs.  MultiLineString).
+///
+/// An empty slice has an empty bounding box, which merges away to nothing,
+/// so empty arrays anywhere in the structure are simply skipped.
+///
+/// Splitting stops once a slice is no longer than the grain size of
+/// `BboxOptions::default()`, and the rest is a sequential loop. Splitting
+/// down to single elements spends more time handing out tasks than
+/// computing min/max; see `compute_bbox_with` to choose the grain size.
+///
+/// An error from `func` has the element's index prepended to its path. If
+/// both halves fail, the error from the left half (the lower index) wins so
+/// the result does not depend on scheduling.
+pub fn compute_bbox<T, F>(v: &[T], func: &F) -> Result<Bbox, BboxError>
+    where F: Fn(&T) -> Result<Bbox, BboxError> + Sync, T: Sync {
+    compute_bbox_with(v, &BboxOptions::default(), func)
+}
+
+
+/// `compute_bbox` with the given options.
+pub fn compute_bbox_with<T, F>(v: &[T], options: &BboxOptions, func: &F) -> Result<Bbox, BboxError>
+    where F: Fn(&T) -> Result<Bbox, BboxError> + Sync, T: Sync {
+    compute_bbox_from(v, 0, options, func)
+}
+
+
+// `offset` is the index of v[0] within the slice originally passed to
+// compute_bbox, so that errors point at the right element.
+pub(crate) fn compute_bbox_from<T, F>(v: &[T], offset: usize, options: &BboxOptions, func: &F)
+    -> Result<Bbox, BboxError>
+    where F: Fn(&T) -> Result<Bbox, BboxError> + Sync, T: Sync {
+    let grain_size = options.grain_size.for_len(v.len());
+    match options.strategy {
+        Strategy::Join => split_bbox(v, offset, grain_size, func),
+        Strategy::Reduce => reduce_bbox(v, offset, grain_size, func),
+        Strategy::Sequential => fold_bbox(v, offset, func),
+    }
+}
+
+
+/// `compute_bbox_with`, splitting slices where the total `weight` of their
+/// elements, rather than their number, is halved. `weight` should be roughly
+/// proportional to the work `func` does, e.g. the number of vertices. Grain
+/// sizes are then measured in units of weight.
+///
+/// With `SplitBy::Elements` in the options, this is just
+/// `compute_bbox_with`.
+pub fn compute_bbox_weighted<T, W, F>(v: &[T], options: &BboxOptions, weight: &W, func: &F)
+    -> Result<Bbox, BboxError>
+    where W: Fn(&T) -> usize, F: Fn(&T) -> Result<Bbox, BboxError> + Sync, T: Sync {
+    compute_weighted_bbox_from(v, 0, options, weight, func)
+}
+
+
+pub(crate) fn compute_weighted_bbox_from<T, W, F>(v: &[T], offset: usize, options: &BboxOptions,
+                                                   weight: &W, func: &F) -> Result<Bbox, BboxError>
+    where W: Fn(&T) -> usize, F: Fn(&T) -> Result<Bbox, BboxError> + Sync, T: Sync {
+    if options.strategy != Strategy::Join || options.split_by == SplitBy::Elements {
+        return compute_bbox_from(v, offset, options, func);
+    }
+
+    // prefix[i] is the weight of v[..i]. Every element weighs at least 1 so
+    // that elements with nothing in them still count for something.
+    let mut prefix = Vec::with_capacity(v.len() + 1);
+    let mut total = 0;
+    prefix.push(total);
+    for x in v {
+        total += weight(x) + 1;
+        prefix.push(total);
+    }
+    split_weighted_bbox(v, &prefix, offset, options.grain_size.for_len(total), func)
+}
+
+
+fn split_bbox<T, F>(v: &[T], offset: usize, grain_size: usize, func: &F) -> Result<Bbox, BboxError>
+    where F: Fn(&T) -> Result<Bbox, BboxError> + Sync, T: Sync {
+    if v.len() <= grain_size {
+        return fold_bbox(v, offset, func);
+    }
+    let mid = v.len() / 2;
+    let (left, right) = v.split_at(mid);
+    let (left_bbox, right_bbox) = rayon::join(|| split_bbox(left, offset, grain_size, func),
+                                              || split_bbox(right, offset + mid, grain_size, func));
+    Ok(left_bbox?.merge(&right_bbox?))
+}
+
+
+// `prefix` holds the running weights from the start of `v` to its end, so
+// it is one longer than `v`.
+fn split_weighted_bbox<T, F>(v: &[T], prefix: &[usize], offset: usize, grain_size: usize, func: &F)
+    -> Result<Bbox, BboxError>
+    where F: Fn(&T) -> Result<Bbox, BboxError> + Sync, T: Sync {
+    let start = prefix[0];
+    if v.len() <= 1 || prefix[v.len()] - start <= grain_size {
+        return fold_bbox(v, offset, func);
+    }
+    // The first index where the running weight reaches half, keeping both
+    // halves non-empty.
+    let half = start + (prefix[v.len()] - start) / 2;
+    let mid = match prefix.binary_search(&half) {
+        Ok(i) | Err(i) => i.max(1).min(v.len() - 1),
+    };
+    let (left, right) = v.split_at(mid);
+    let (left_bbox, right_bbox) = rayon::join(
+        || split_weighted_bbox(left, &prefix[..mid + 1], offset, grain_size, func),
+        || split_weighted_bbox(right, &prefix[mid..], offset + mid, grain_size, func));
+    Ok(left_bbox?.merge(&right_bbox?))
+}
+
+
+fn reduce_bbox<T, F>(v: &[T], offset: usize, grain_size: usize, func: &F) -> Result<Bbox, BboxError>
+    where F: Fn(&T) -> Result<Bbox, BboxError> + Sync, T: Sync {
+    v.par_iter()
+        .enumerate()
+        .with_min_len(grain_size)
+        .map(|(i, x)| func(x).map_err(|e| e.at_index(offset + i)))
+        // reduce keeps the order of its operands, so the left error wins as
+        // in split_bbox.
+        .reduce(|| Ok(Bbox::empty()), |left, right| Ok(left?.merge(&right?)))
+}
+
+
+// The sequential base case. Stopping at the first error gives the lowest
+// index, as the parallel case does.
+fn fold_bbox<T, F>(v: &[T], offset: usize, func: &F) -> Result<Bbox, BboxError>
+    where F: Fn(&T) -> Result<Bbox, BboxError> {
+    let mut bbox = Bbox::empty();
+    for (i, x) in v.iter().enumerate() {
+        let b = func(x).map_err(|e| e.at_index(offset + i))?;
+        bbox = bbox.merge(&b);
+    }
+    Ok(bbox)
+}
@@ -0,0 +1,186 @@
+use std::convert::TryInto;
+
+use geojson::{Geometry, Position, Value};
+
+use error::ReadError;
+
+
+// Flags of PostGIS's extended WKB, in the high bits of the geometry type.
+const EWKB_Z: u32 = 0x8000_0000;
+const EWKB_M: u32 = 0x4000_0000;
+const EWKB_SRID: u32 = 0x2000_0000;
+
+
+/// Parse hex-encoded WKB or EWKB, as PostGIS writes geometries out. See
+/// `parse_wkb`.
+pub fn parse_hex_wkb(hex: &str) -> Result<Value, ReadError> {
+    let hex = hex.as_bytes();
+    if !hex.len().is_multiple_of(2) {
+        return Err(ReadError::Wkb("odd number of hex digits".to_string()));
+    }
+    let data = hex.chunks(2)
+        .map(|pair| {
+            let digits = std::str::from_utf8(pair).ok();
+            digits.and_then(|d| u8::from_str_radix(d, 16).ok())
+                .ok_or_else(|| ReadError::Wkb("invalid hex digit".to_string()))
+        })
+        .collect::<Result<Vec<u8>, ReadError>>()?;
+    parse_wkb(&data)
+}
+
+
+/// Parse a WKB geometry of any of the seven types into a `geojson` Value.
+/// Both ISO WKB dimensions (type codes 1001 and up) and PostGIS EWKB flags
+/// and SRIDs are understood. z values are kept and m values dropped.
+///
+/// Empty geometries become Values with no coordinates, as in `parse_wkt`.
+/// An empty Point, written as NaN coordinates, becomes an empty MultiPoint.
+pub fn parse_wkb(data: &[u8]) -> Result<Value, ReadError> {
+    let mut reader = Reader { data, pos: 0, little_endian: true };
+    let value = reader.geometry()?;
+    if reader.pos != data.len() {
+        return Err(reader.error("unexpected bytes after the geometry"));
+    }
+    Ok(value)
+}
+
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    // The byte order of the geometry being read. Every geometry, including
+    // those nested in Multi* and GeometryCollections, sets its own.
+    little_endian: bool,
+}
+
+
+impl<'a> Reader<'a> {
+    fn error(&self, message: &str) -> ReadError {
+        ReadError::Wkb(format!("{} at byte {}", message, self.pos))
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], ReadError> {
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or_else(|| self.error("unexpected end of data"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, ReadError> {
+        let bytes = self.bytes(4)?.try_into().unwrap();
+        Ok(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+
+    fn f64(&mut self) -> Result<f64, ReadError> {
+        let bytes = self.bytes(8)?.try_into().unwrap();
+        Ok(if self.little_endian { f64::from_le_bytes(bytes) } else { f64::from_be_bytes(bytes) })
+    }
+
+    // A count of items that are each at least `min_len` bytes long, checked
+    // against what is left so that a corrupt count fails early.
+    fn count(&mut self, min_len: usize) -> Result<usize, ReadError> {
+        let n = self.u32()? as usize;
+        if n.saturating_mul(min_len) > self.data.len() - self.pos {
+            return Err(self.error("count is larger than the data"));
+        }
+        Ok(n)
+    }
+
+    fn geometry(&mut self) -> Result<Value, ReadError> {
+        self.little_endian = match self.bytes(1)?[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(self.error("invalid byte order")),
+        };
+        let code = self.u32()?;
+        let (mut z, mut m) = (code & EWKB_Z != 0, code & EWKB_M != 0);
+        if code & EWKB_SRID != 0 {
+            self.u32()?;
+        }
+        let code = code & 0x0fff_ffff;
+        match code / 1000 {
+            0 => (),
+            1 => z = true,
+            2 => m = true,
+            3 => {
+                z = true;
+                m = true;
+            }
+            _ => return Err(self.error(&format!("unknown geometry type {}", code))),
+        }
+        let dims = (z, m);
+
+        Ok(match code % 1000 {
+            1 => {
+                let position = self.position(dims)?;
+                if position[0].is_nan() && position[1].is_nan() {
+                    Value::MultiPoint(vec![])
+                } else {
+                    Value::Point(position)
+                }
+            }
+            2 => Value::LineString(self.positions(dims)?),
+            3 => Value::Polygon(self.rings(dims)?),
+            4 => {
+                let mut points = Vec::new();
+                for _ in 0..self.count(5)? {
+                    match self.geometry()? {
+                        Value::Point(position) => points.push(position),
+                        Value::MultiPoint(ref empty) if empty.is_empty() => (),
+                        _ => return Err(self.error("expected a Point in a MultiPoint")),
+                    }
+                }
+                Value::MultiPoint(points)
+            }
+            5 => {
+                let mut lines = Vec::new();
+                for _ in 0..self.count(9)? {
+                    match self.geometry()? {
+                        Value::LineString(line) => lines.push(line),
+                        _ => return Err(self.error("expected a LineString in a MultiLineString")),
+                    }
+                }
+                Value::MultiLineString(lines)
+            }
+            6 => {
+                let mut polygons = Vec::new();
+                for _ in 0..self.count(9)? {
+                    match self.geometry()? {
+                        Value::Polygon(polygon) => polygons.push(polygon),
+                        _ => return Err(self.error("expected a Polygon in a MultiPolygon")),
+                    }
+                }
+                Value::MultiPolygon(polygons)
+            }
+            7 => {
+                let mut geometries = Vec::new();
+                for _ in 0..self.count(5)? {
+                    geometries.push(Geometry::new(self.geometry()?));
+                }
+                Value::GeometryCollection(geometries)
+            }
+            _ => return Err(self.error(&format!("unknown geometry type {}", code))),
+        })
+    }
+
+    fn rings(&mut self, dims: (bool, bool)) -> Result<Vec<Vec<Position>>, ReadError> {
+        (0..self.count(4)?).map(|_| self.positions(dims)).collect()
+    }
+
+    fn positions(&mut self, dims: (bool, bool)) -> Result<Vec<Position>, ReadError> {
+        let (z, m) = dims;
+        let len = 8 * (2 + z as usize + m as usize);
+        (0..self.count(len)?).map(|_| self.position(dims)).collect()
+    }
+
+    // x and y, and z if there is one. An m value is read and dropped.
+    fn position(&mut self, (z, m): (bool, bool)) -> Result<Position, ReadError> {
+        let mut position = vec![self.f64()?, self.f64()?];
+        if z {
+            position.push(self.f64()?);
+        }
+        if m {
+            self.f64()?;
+        }
+        Ok(position)
+    }
+}
@@ -0,0 +1,285 @@
+use std::io::BufRead;
+use std::str;
+
+use geojson::{Geometry, Position, Value};
+
+use error::ReadError;
+use sequence::records_bbox;
+use skipped::Skipped;
+use stream::Streamed;
+use to_bbox::ToBbox;
+use wkb::parse_hex_wkb;
+
+
+/// Compute the total bounding box of geometries given one per line, as WKT
+/// (or PostGIS EWKT, with an `SRID=...;` prefix) or as hex-encoded WKB or
+/// EWKB, the way PostGIS writes geometries out. Lines that are all hex
+/// digits are read as WKB and any other line as WKT, so the two can be
+/// mixed. Blank lines are ignored.
+///
+/// Lines are read in chunks of `chunk_size` and parsed in parallel, as by
+/// `sequence_bbox`, and each geometry's bbox is computed with `ToBbox`.
+/// Errors are reported with the line number.
+pub fn wkt_bbox<R: BufRead + Send>(reader: R, chunk_size: usize) -> Result<Streamed, ReadError> {
+    records_bbox(reader, b'\n', chunk_size, &|line: &[u8]| {
+        let value = parse_line(line)?;
+        Ok(Streamed { bbox: value.to_bbox()?, skipped: Skipped::default() })
+    })
+}
+
+
+fn parse_line(line: &[u8]) -> Result<Value, ReadError> {
+    let line = str::from_utf8(line).map_err(|_| ReadError::Wkt("line is not UTF-8".to_string()))?.trim();
+    if line.bytes().all(|b| b.is_ascii_hexdigit()) {
+        parse_hex_wkb(line)
+    } else {
+        parse_wkt(line)
+    }
+}
+
+
+/// Parse a WKT geometry of any of the seven types, in 2D, Z, M or ZM, into
+/// a `geojson` Value. z values are kept and m values dropped. A leading
+/// EWKT `SRID=...;` is skipped.
+///
+/// EMPTY geometries become Values with no coordinates, whose bbox is empty.
+/// GeoJSON has no empty Point, so `POINT EMPTY` becomes an empty
+/// MultiPoint.
+pub fn parse_wkt(text: &str) -> Result<Value, ReadError> {
+    let mut parser = Parser { text: text.as_bytes(), pos: 0 };
+    parser.skip_srid();
+    let value = parser.geometry()?;
+    if parser.peek().is_some() {
+        return Err(parser.error("unexpected text after the geometry"));
+    }
+    Ok(value)
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Point,
+    LineString,
+    Polygon,
+    MultiPoint,
+    MultiLineString,
+    MultiPolygon,
+    GeometryCollection,
+}
+
+
+impl Kind {
+    fn from_name(name: &str) -> Option<Kind> {
+        match name {
+            "POINT" => Some(Kind::Point),
+            "LINESTRING" => Some(Kind::LineString),
+            "POLYGON" => Some(Kind::Polygon),
+            "MULTIPOINT" => Some(Kind::MultiPoint),
+            "MULTILINESTRING" => Some(Kind::MultiLineString),
+            "MULTIPOLYGON" => Some(Kind::MultiPolygon),
+            "GEOMETRYCOLLECTION" => Some(Kind::GeometryCollection),
+            _ => None,
+        }
+    }
+
+    fn empty(self) -> Value {
+        match self {
+            Kind::Point | Kind::MultiPoint => Value::MultiPoint(vec![]),
+            Kind::LineString => Value::LineString(vec![]),
+            Kind::Polygon => Value::Polygon(vec![]),
+            Kind::MultiLineString => Value::MultiLineString(vec![]),
+            Kind::MultiPolygon => Value::MultiPolygon(vec![]),
+            Kind::GeometryCollection => Value::GeometryCollection(vec![]),
+        }
+    }
+}
+
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> ReadError {
+        ReadError::Wkt(format!("{} at column {}", message, self.pos + 1))
+    }
+
+    // The next non-whitespace byte, without consuming it.
+    fn peek(&mut self) -> Option<u8> {
+        while self.pos < self.text.len() && self.text[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        self.text.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ReadError> {
+        if self.eat(byte) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    // The next word, upper-cased, or an empty string if there isn't one.
+    fn word(&mut self) -> String {
+        self.peek();
+        let start = self.pos;
+        while self.pos < self.text.len() && self.text[self.pos].is_ascii_alphabetic() {
+            self.pos += 1;
+        }
+        str::from_utf8(&self.text[start..self.pos]).unwrap().to_ascii_uppercase()
+    }
+
+    // Consume the word if it is next.
+    fn eat_word(&mut self, expected: &str) -> bool {
+        let start = self.pos;
+        if self.word() == expected {
+            true
+        } else {
+            self.pos = start;
+            false
+        }
+    }
+
+    fn skip_srid(&mut self) {
+        if self.text.len() >= 5 && self.text[..5].eq_ignore_ascii_case(b"SRID=") {
+            if let Some(end) = self.text.iter().position(|&b| b == b';') {
+                self.pos = end + 1;
+            }
+        }
+    }
+
+    fn geometry(&mut self) -> Result<Value, ReadError> {
+        let start = self.pos;
+        let mut name = self.word();
+        // The dimension may be written separately, `POINT ZM`, or attached,
+        // `POINTZM`.
+        let mut dimension = String::new();
+        if Kind::from_name(&name).is_none() {
+            for suffix in &["ZM", "Z", "M"] {
+                if name.ends_with(suffix) && Kind::from_name(&name[..name.len() - suffix.len()]).is_some() {
+                    dimension = suffix.to_string();
+                    name.truncate(name.len() - suffix.len());
+                    break;
+                }
+            }
+        }
+        let kind = match Kind::from_name(&name) {
+            Some(kind) => kind,
+            None => {
+                self.pos = start;
+                return Err(self.error("expected a geometry type"));
+            }
+        };
+        let mut next = self.word();
+        if dimension.is_empty() && (next == "Z" || next == "M" || next == "ZM") {
+            dimension = next;
+            next = self.word();
+        }
+        match next.as_str() {
+            "EMPTY" => return Ok(kind.empty()),
+            "" => (),
+            _ => return Err(self.error(&format!("unexpected '{}'", next))),
+        }
+
+        let m = dimension == "M";
+        Ok(match kind {
+            Kind::Point => {
+                self.expect(b'(')?;
+                let position = self.position(m)?;
+                self.expect(b')')?;
+                Value::Point(position)
+            }
+            Kind::LineString => Value::LineString(self.positions(m)?),
+            Kind::Polygon => Value::Polygon(self.rings(m)?),
+            Kind::MultiPoint => {
+                // Points may be written with or without their parentheses.
+                let points = self.list(|p| {
+                    if p.eat_word("EMPTY") {
+                        Ok(None)
+                    } else if p.eat(b'(') {
+                        let position = p.position(m)?;
+                        p.expect(b')')?;
+                        Ok(Some(position))
+                    } else {
+                        p.position(m).map(Some)
+                    }
+                })?;
+                Value::MultiPoint(points.into_iter().flatten().collect())
+            }
+            Kind::MultiLineString => Value::MultiLineString(self.list(|p| p.empty_or(|p| p.positions(m)))?),
+            Kind::MultiPolygon => Value::MultiPolygon(self.list(|p| p.empty_or(|p| p.rings(m)))?),
+            Kind::GeometryCollection => {
+                Value::GeometryCollection(self.list(|p| p.geometry().map(Geometry::new))?)
+            }
+        })
+    }
+
+    // A parenthesized, comma-separated list of items.
+    fn list<T, F>(&mut self, mut item: F) -> Result<Vec<T>, ReadError>
+        where F: FnMut(&mut Self) -> Result<T, ReadError> {
+        self.expect(b'(')?;
+        let mut items = vec![item(self)?];
+        while self.eat(b',') {
+            items.push(item(self)?);
+        }
+        self.expect(b')')?;
+        Ok(items)
+    }
+
+    // An item, or nothing for EMPTY.
+    fn empty_or<T: Default, F>(&mut self, item: F) -> Result<T, ReadError>
+        where F: FnOnce(&mut Self) -> Result<T, ReadError> {
+        if self.eat_word("EMPTY") { Ok(T::default()) } else { item(self) }
+    }
+
+    fn rings(&mut self, m: bool) -> Result<Vec<Vec<Position>>, ReadError> {
+        self.list(|p| p.empty_or(|p| p.positions(m)))
+    }
+
+    fn positions(&mut self, m: bool) -> Result<Vec<Position>, ReadError> {
+        self.list(|p| p.position(m))
+    }
+
+    // A position of two to four numbers, keeping x, y and z. With `m`, as in
+    // `POINT M`, a third number is an m value rather than z.
+    fn position(&mut self, m: bool) -> Result<Position, ReadError> {
+        let mut position = Vec::with_capacity(4);
+        while let Some(b'0'..=b'9') | Some(b'-') | Some(b'+') | Some(b'.') = self.peek() {
+            position.push(self.number()?);
+        }
+        match position.len() {
+            2 => (),
+            3 if m => position.truncate(2),
+            3 => (),
+            4 => position.truncate(3),
+            _ => return Err(self.error("expected a position of 2 to 4 numbers")),
+        }
+        Ok(position)
+    }
+
+    fn number(&mut self) -> Result<f64, ReadError> {
+        let start = self.pos;
+        while self.pos < self.text.len() && matches!(self.text[self.pos], b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') {
+            self.pos += 1;
+        }
+        match str::from_utf8(&self.text[start..self.pos]).unwrap().parse() {
+            Ok(n) => Ok(n),
+            Err(_) => {
+                self.pos = start;
+                Err(self.error("invalid number"))
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+extern crate geojson;
+extern crate par_bbox;
+
+use geojson::Value;
+use par_bbox::{Bbox, ToBbox, compute_bbox};
+
+
+fn points(xs: &[f64]) -> Vec<Bbox> {
+    xs.iter().map(|&x| Bbox::from_point(x, 0.0)).collect()
+}
+
+
+fn merged(bboxes: &[Bbox]) -> Bbox {
+    compute_bbox(bboxes, &|b| Ok(*b)).unwrap()
+}
+
+
+#[test]
+fn fiji_crosses_antimeridian() {
+    let fiji = Value::MultiPoint(vec![
+        vec![177.0, -17.0], vec![178.5, -18.0], vec![-179.8, -16.5], vec![-178.2, -19.0],
+    ]);
+    let bbox = fiji.to_bbox().unwrap();
+    assert_eq!(bbox, Bbox::new(-179.8, -19.0, 178.5, -16.5));
+
+    let wrapped = bbox.antimeridian();
+    assert!(wrapped.crosses_antimeridian());
+    assert_eq!(wrapped, Bbox::new(177.0, -19.0, -178.2, -16.5));
+    assert_eq!((wrapped.xmin(), wrapped.ymin(), wrapped.xmax(), wrapped.ymax()), (177.0, -19.0, -178.2, -16.5));
+}
+
+
+#[test]
+fn ordinary_data_is_unchanged() {
+    let bbox = merged(&points(&[-71.2, -71.1, 10.0, -5.0])).antimeridian();
+    assert!(!bbox.crosses_antimeridian());
+    assert_eq!(bbox, Bbox::new(-71.2, 0.0, 10.0, 0.0));
+}
+
+
+#[test]
+fn merge_is_associative() {
+    let bboxes: Vec<Bbox> = points(&[170.0, -175.0, 179.0, -160.0, 165.0])
+        .into_iter().map(|b| b.antimeridian()).collect();
+    let (a, b, c) = (merged(&bboxes[..2]), merged(&bboxes[2..3]), merged(&bboxes[3..]));
+
+    let left = a.merge(&b).merge(&c);
+    let right = a.merge(&b.merge(&c));
+    assert_eq!(left, right);
+    assert_eq!(left, Bbox::new(165.0, 0.0, -160.0, 0.0));
+    assert_eq!(merged(&bboxes), left);
+}
+
+
+#[test]
+fn new_accepts_crossing_bbox() {
+    let crossing = Bbox::new(170.0, 0.0, -170.0, 1.0);
+    assert!(crossing.crosses_antimeridian());
+
+    // A point inside the crossing interval does not widen it.
+    let merged = crossing.merge(&Bbox::from_point(179.0, 0.5));
+    assert_eq!(merged, crossing);
+}
+
+
+#[test]
+fn crossing_bbox_may_reach_past_the_prime_meridian() {
+    // Both 170..180 and -180..10, so the interval also crosses longitude 0.
+    let crossing = Bbox::new(170.0, 0.0, 10.0, 1.0);
+    assert_eq!((crossing.xmin(), crossing.xmax()), (170.0, 10.0));
+    for &x in &[5.0, -90.0, 175.0, 0.0, 10.0] {
+        assert_eq!(crossing.merge(&Bbox::from_point(x, 0.5)), crossing);
+    }
+    assert_eq!(crossing.merge(&Bbox::from_point(20.0, 0.5)), Bbox::new(170.0, 0.0, 20.0, 1.0));
+    assert_eq!(crossing.merge(&Bbox::from_point(160.0, 0.5)), Bbox::new(160.0, 0.0, 10.0, 1.0));
+
+    // Likewise from west of the prime meridian.
+    let crossing = Bbox::new(-60.0, 0.0, -170.0, 1.0);
+    assert_eq!((crossing.xmin(), crossing.xmax()), (-60.0, -170.0));
+    assert_eq!(crossing.merge(&Bbox::from_point(90.0, 0.5)), crossing);
+    assert_eq!(crossing.merge(&Bbox::from_point(-65.0, 0.5)), Bbox::new(-65.0, 0.0, -170.0, 1.0));
+
+    // Points spread over more than 180 degrees.
+    let bbox = merged(&points(&[150.0, -170.0, -60.0, 10.0])).antimeridian();
+    assert_eq!((bbox.xmin(), bbox.xmax()), (150.0, 10.0));
+}
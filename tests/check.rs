@@ -0,0 +1,83 @@
+extern crate geojson;
+extern crate par_bbox;
+
+mod common;
+
+use geojson::GeoJson;
+use par_bbox::{MemberOptions, check_bbox_members, fill_bbox_members};
+use common::load_polys;
+
+
+#[test]
+fn filled_members_all_match() {
+    let mut geojson = load_polys();
+    assert_eq!(check_bbox_members(&geojson).unwrap().checked, 0);
+
+    let options = MemberOptions { geometries: true, antimeridian: false };
+    fill_bbox_members(&mut geojson, &options).unwrap();
+    let report = check_bbox_members(&geojson).unwrap();
+    assert_eq!(report.checked, 7);
+    assert!(report.mismatches.is_empty());
+}
+
+
+#[test]
+fn reports_stale_members_with_paths() {
+    let geojson: GeoJson = r#"{
+        "type": "FeatureCollection",
+        "bbox": [0.0, 0.0, 10.0, 10.0],
+        "features": [
+            {"type": "Feature", "properties": {}, "bbox": [0.0, 0.0, 1.0, 1.0],
+             "geometry": {"type": "Point", "coordinates": [0.5, 0.5]}},
+            {"type": "Feature", "properties": {}, "bbox": [0.0, 0.0, 1.0],
+             "geometry": {"type": "GeometryCollection", "geometries": [
+                 {"type": "Point", "coordinates": [1.0, 1.0], "bbox": [1.0, 1.0, 1.0, 1.0]},
+                 {"type": "Point", "coordinates": [2.0, 3.0], "bbox": [2.0, 3.0, 2.5, 3.0]}
+             ]}}
+        ]
+    }"#.parse().unwrap();
+    let report = check_bbox_members(&geojson).unwrap();
+    assert_eq!(report.checked, 5);
+
+    let found: Vec<(String, f64)> = report.mismatches.iter()
+        .map(|m| (m.path.to_string(), m.difference))
+        .collect();
+    assert_eq!(found[0], ("bbox".to_string(), 8.0));
+    assert_eq!(found[1], ("features[0].bbox".to_string(), 0.5));
+    assert_eq!(found[2].0, "features[1].bbox");
+    assert!(found[2].1.is_infinite());
+    assert_eq!(found[3], ("features[1].geometry.geometries[1].bbox".to_string(), 0.5));
+    assert_eq!(found.len(), 4);
+
+    assert_eq!(report.exceeding(0.5).len(), 2);
+}
+
+
+#[test]
+fn crossing_members_compare_against_antimeridian_extent() {
+    let geojson: GeoJson = r#"{
+        "type": "Feature", "properties": {}, "bbox": [177.0, -19.0, -178.0, -16.0],
+        "geometry": {"type": "MultiPoint", "coordinates": [[177.0, -16.0], [-178.0, -19.0]]}
+    }"#.parse().unwrap();
+    let report = check_bbox_members(&geojson).unwrap();
+    assert_eq!(report.checked, 1);
+    assert!(report.mismatches.is_empty());
+}
+
+
+#[test]
+fn declared_z_range_needs_3d_coordinates() {
+    let geojson: GeoJson = r#"{
+        "type": "Feature", "properties": {}, "bbox": [1.0, 2.0, 0.0, 3.0, 4.0, 0.0],
+        "geometry": {"type": "LineString", "coordinates": [[1.0, 2.0], [3.0, 4.0]]}
+    }"#.parse().unwrap();
+    let report = check_bbox_members(&geojson).unwrap();
+    assert_eq!(report.mismatches.len(), 1);
+    assert_eq!(report.mismatches[0].difference, f64::INFINITY);
+
+    let geojson: GeoJson = r#"{
+        "type": "Feature", "properties": {}, "bbox": [1.0, 2.0, 5.0, 3.0, 4.0, 6.0],
+        "geometry": {"type": "LineString", "coordinates": [[1.0, 2.0, 5.0], [3.0, 4.0, 6.0]]}
+    }"#.parse().unwrap();
+    assert!(check_bbox_members(&geojson).unwrap().mismatches.is_empty());
+}
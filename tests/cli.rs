@@ -0,0 +1,61 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+
+// Run the binary with the given arguments and stdin.
+fn par_bbox(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_par_bbox"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+
+const POLYS: &str = "Bbox { xmin: -71.1906871, xmax: -71.1894741, ymin: 42.228073, ymax: 42.2285172 }";
+
+
+#[test]
+fn several_inputs_print_each_and_the_union() {
+    let point = r#"{"type": "Point", "coordinates": [10, -5]}"#;
+    let output = par_bbox(&["data/*.geojson", "-"], point);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), format!(
+        "data/polys.geojson: {}\n-: Bbox {{ xmin: 10.0, xmax: 10.0, ymin: -5.0, ymax: -5.0 }}\n\
+         Total bbox: Bbox {{ xmin: -71.1906871, xmax: 10.0, ymin: -5.0, ymax: 42.2285172 }}\n", POLYS));
+
+    let output = par_bbox(&["--format", "json", "data", "-"], point);
+    assert_eq!(stdout(&output), "[-71.1906871,-5.0,10.0,42.2285172]\n");
+}
+
+
+#[test]
+fn csv_delimiter_can_be_given() {
+    let csv = "name;lat;lon\na;42.1;-71.2\nb;42.3;-71.0\n";
+    let output = par_bbox(&["--input", "csv", "--csv-delimiter", ";", "--format", "json", "-"], csv);
+    assert_eq!(stdout(&output), "[-71.2,42.1,-71.0,42.3]\n");
+
+    let output = par_bbox(&["--input", "csv", "--csv-delimiter", "tab", "--format", "json", "-"],
+                          &csv.replace(';', "\t"));
+    assert_eq!(stdout(&output), "[-71.2,42.1,-71.0,42.3]\n");
+}
+
+
+#[test]
+fn errors_go_to_stderr() {
+    for args in &[&["--format", "json", "no-such-file.geojson"][..], &["--stream", "--scan", "-"], &["--bench", "2", "-"]] {
+        let output = par_bbox(args, "");
+        assert!(!output.status.success(), "{:?}", args);
+        assert_eq!(stdout(&output), "", "{:?}", args);
+        assert!(!output.stderr.is_empty(), "{:?}", args);
+    }
+}
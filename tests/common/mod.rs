@@ -0,0 +1,47 @@
+// Helpers shared by the integration tests. Each test crate uses only some of
+// them.
+#![allow(dead_code)]
+
+use std::fs::File;
+use std::io::Read;
+
+use geojson::{Feature, FeatureCollection, GeoJson, Geometry};
+
+
+// The bytes of data/polys.geojson.
+pub fn polys_bytes() -> Vec<u8> {
+    let mut data = Vec::new();
+    File::open("data/polys.geojson").unwrap().read_to_end(&mut data).unwrap();
+    data
+}
+
+
+// The text of data/polys.geojson.
+pub fn polys_text() -> String {
+    let mut data = String::new();
+    File::open("data/polys.geojson").unwrap().read_to_string(&mut data).unwrap();
+    data
+}
+
+
+pub fn load_polys() -> GeoJson {
+    polys_text().parse().unwrap()
+}
+
+
+pub fn polys_collection() -> FeatureCollection {
+    match load_polys() {
+        GeoJson::FeatureCollection(fc) => fc,
+        _ => panic!("expected a FeatureCollection"),
+    }
+}
+
+
+pub fn feature(geometry: Option<Geometry>) -> Feature {
+    Feature { bbox: None, geometry, id: None, properties: None, foreign_members: None }
+}
+
+
+pub fn collection(features: Vec<Feature>) -> FeatureCollection {
+    FeatureCollection { bbox: None, features, foreign_members: None }
+}
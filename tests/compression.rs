@@ -0,0 +1,79 @@
+extern crate flate2;
+extern crate geojson;
+extern crate par_bbox;
+extern crate zstd;
+
+mod common;
+
+use std::io::{Read, Write};
+
+use flate2::write::GzEncoder;
+use par_bbox::{Compression, decompress, stream_bbox};
+use common::polys_bytes;
+
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+
+fn read_all(compressed: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    decompress(compressed).unwrap().read_to_end(&mut data).unwrap();
+    data
+}
+
+
+#[test]
+fn detects_magic_bytes() {
+    let data = polys_bytes();
+    assert_eq!(Compression::detect(&gzip(&data)), Some(Compression::Gzip));
+    assert_eq!(Compression::detect(&zstd::encode_all(&data[..], 0).unwrap()), Some(Compression::Zstd));
+    assert_eq!(Compression::detect(&data), None);
+    assert_eq!(Compression::detect(&[0x1f]), None);
+}
+
+
+#[test]
+fn decompresses_gzip_and_zstd() {
+    let data = polys_bytes();
+    assert_eq!(read_all(&gzip(&data)), data);
+    assert_eq!(read_all(&zstd::encode_all(&data[..], 0).unwrap()), data);
+}
+
+
+#[test]
+fn passes_through_uncompressed() {
+    let data = polys_bytes();
+    assert_eq!(read_all(&data), data);
+    assert_eq!(read_all(b"{}"), b"{}");
+    assert_eq!(read_all(b""), b"");
+}
+
+
+#[test]
+fn reads_concatenated_gzip_members() {
+    let mut compressed = gzip(b"[1, ");
+    compressed.extend(gzip(b"2]"));
+    assert_eq!(read_all(&compressed), b"[1, 2]");
+}
+
+
+#[test]
+fn streams_compressed_input() {
+    let data = polys_bytes();
+    let expected = stream_bbox(&data[..], 2).unwrap();
+    let streamed = stream_bbox(decompress(&gzip(&data)[..]).unwrap(), 2).unwrap();
+    assert_eq!(streamed.bbox, expected.bbox);
+}
+
+
+#[test]
+fn truncated_gzip_is_an_error() {
+    let compressed = gzip(&polys_bytes());
+    let mut data = Vec::new();
+    let result = decompress(&compressed[..compressed.len() / 2]).unwrap().read_to_end(&mut data);
+    assert!(result.is_err());
+}
@@ -0,0 +1,70 @@
+extern crate par_bbox;
+
+use par_bbox::{Bbox, CsvOptions, ReadError, csv_bbox};
+
+
+fn named(lon: &str, lat: &str) -> CsvOptions {
+    CsvOptions { lon: Some(lon.to_string()), lat: Some(lat.to_string()), ..CsvOptions::default() }
+}
+
+
+#[test]
+fn detects_coordinate_columns() {
+    for &text in &["name,lon,lat\na,-71.5,42.25\nb,-70,43\n",
+                   "Name,Latitude,Longitude\na,42.25,-71.5\nb,43,-70\n",
+                   "id,LNG,LAT\n1,-71.5,42.25\n2,-70,43\n",
+                   "y,x\n42.25,-71.5\n43,-70\n"] {
+        let points = csv_bbox(text.as_bytes(), &CsvOptions::default(), 100).unwrap();
+        assert_eq!(points.bbox, Bbox::new(-71.5, 42.25, -70.0, 43.0), "{}", text);
+        assert_eq!(points.points, 2);
+    }
+}
+
+
+#[test]
+fn named_columns() {
+    let text = "site;east;north;lon\n\"a; the first\";10;20;x\nb;12.5;-3;x\n";
+    let options = CsvOptions { delimiter: b';', ..named("east", "North") };
+    let points = csv_bbox(text.as_bytes(), &options, 100).unwrap();
+    assert_eq!(points.bbox, Bbox::new(10.0, -3.0, 12.5, 20.0));
+
+    let options = CsvOptions { delimiter: b';', ..named("east", "up") };
+    match csv_bbox(text.as_bytes(), &options, 100) {
+        Err(ReadError::Csv(ref message)) => assert!(message.contains("'up'"), "{}", message),
+        other => panic!("expected a CSV error, got {:?}", other),
+    }
+    match csv_bbox("a,b\n1,2\n".as_bytes(), &CsvOptions::default(), 100) {
+        Err(ReadError::Csv(ref message)) => assert!(message.contains("longitude"), "{}", message),
+        other => panic!("expected a CSV error, got {:?}", other),
+    }
+}
+
+
+#[test]
+fn bad_rows_are_counted_not_fatal() {
+    let text = "\
+name,lon,lat
+a,1,2
+b,,
+c,north,5
+d,3
+e,4,-1
+f,inf,0
+\"g
+h\",5,6
+";
+    for &chunk_size in &[1, 2, 100] {
+        let points = csv_bbox(text.as_bytes(), &CsvOptions::default(), chunk_size).unwrap();
+        assert_eq!(points.bbox, Bbox::new(1.0, -1.0, 5.0, 6.0));
+        assert_eq!((points.points, points.blank, points.unparseable), (3, 1, 3));
+        assert_eq!(points.first_unparseable, Some(4));
+    }
+}
+
+
+#[test]
+fn empty_file() {
+    let points = csv_bbox("lon,lat\n".as_bytes(), &CsvOptions::default(), 100).unwrap();
+    assert!(points.bbox.is_empty());
+    assert_eq!(points.points, 0);
+}
@@ -0,0 +1,39 @@
+extern crate geojson;
+extern crate par_bbox;
+
+use geojson::{Geometry, Value};
+use par_bbox::{Bbox, ToBbox};
+
+
+#[test]
+fn z_range_from_3d_positions() {
+    let line = Value::LineString(vec![
+        vec![0.0, 0.0, 12.5], vec![1.0, 2.0, 40.0], vec![-1.0, 1.0, 3.0],
+    ]);
+    let bbox = line.to_bbox().unwrap();
+    assert_eq!(bbox, Bbox::new_3d(-1.0, 0.0, 3.0, 1.0, 2.0, 40.0));
+    assert_eq!(bbox.z_range(), Some((3.0, 40.0)));
+    assert!(!bbox.is_mixed_dimension());
+}
+
+
+#[test]
+fn flat_input_has_no_z() {
+    let bbox = Value::MultiPoint(vec![vec![0.0, 0.0], vec![1.0, 1.0]]).to_bbox().unwrap();
+    assert!(!bbox.has_z());
+    assert_eq!(bbox.z_range(), None);
+    assert_eq!(format!("{:?}", bbox), "Bbox { xmin: 0.0, xmax: 1.0, ymin: 0.0, ymax: 1.0 }");
+}
+
+
+#[test]
+fn mixed_dimensions_use_3d_positions_for_z() {
+    let gc = Geometry::new(Value::GeometryCollection(vec![
+        Geometry::new(Value::Point(vec![5.0, 5.0])),
+        Geometry::new(Value::Point(vec![-5.0, 1.0, 100.0])),
+        Geometry::new(Value::Point(vec![0.0, -2.0, -10.0])),
+    ]));
+    let bbox = gc.to_bbox().unwrap();
+    assert_eq!(bbox, Bbox::new_3d(-5.0, -2.0, -10.0, 5.0, 5.0, 100.0));
+    assert!(bbox.is_mixed_dimension());
+}
@@ -0,0 +1,55 @@
+extern crate geojson;
+extern crate par_bbox;
+
+mod common;
+
+use geojson::{GeoJson, Geometry, Value};
+use par_bbox::{Bbox, Skipped, SkipReason, ToBbox};
+use common::{collection, feature};
+
+
+#[test]
+fn empty_is_merge_identity() {
+    let b = Bbox::new(-1.0, -2.0, 3.0, 4.0);
+    assert!(Bbox::empty().is_empty());
+    assert!(!b.is_empty());
+    assert_eq!(b.merge(&Bbox::empty()), b);
+    assert_eq!(Bbox::empty().merge(&b), b);
+    assert!(Bbox::empty().merge(&Bbox::empty()).is_empty());
+}
+
+
+#[test]
+fn empty_feature_collection() {
+    let geojson = GeoJson::FeatureCollection(collection(vec![]));
+    assert!(geojson.to_bbox().unwrap().is_empty());
+    assert_eq!(Skipped::count(&geojson).total(), 0);
+}
+
+
+#[test]
+fn empty_geometries_are_skipped() {
+    let geojson = GeoJson::FeatureCollection(collection(vec![
+        feature(None),
+        feature(Some(Geometry::new(Value::GeometryCollection(vec![])))),
+        feature(Some(Geometry::new(Value::Point(vec![1.0, 2.0])))),
+        feature(Some(Geometry::new(Value::MultiPolygon(vec![])))),
+        feature(Some(Geometry::new(Value::Polygon(vec![])))),
+        feature(None),
+        feature(Some(Geometry::new(Value::LineString(vec![vec![3.0, -1.0], vec![4.0, 0.0]])))),
+    ]));
+    assert_eq!(geojson.to_bbox().unwrap(), Bbox::new(1.0, -1.0, 4.0, 2.0));
+    assert_eq!(Skipped::count(&geojson),
+               Skipped { null_geometry: 2, empty_geometry: 3 });
+}
+
+
+#[test]
+fn empty_members_of_a_collection_contribute_nothing() {
+    let gc = Geometry::new(Value::GeometryCollection(vec![
+        Geometry::new(Value::MultiPoint(vec![])),
+        Geometry::new(Value::Point(vec![5.0, 6.0])),
+    ]));
+    assert_eq!(gc.to_bbox().unwrap(), Bbox::new(5.0, 6.0, 5.0, 6.0));
+    assert_eq!(SkipReason::of(&feature(Some(gc))), None);
+}
@@ -0,0 +1,58 @@
+extern crate geojson;
+extern crate par_bbox;
+
+mod common;
+
+use geojson::{GeoJson, Geometry, Value};
+use par_bbox::{BboxErrorKind, BboxOptions, GrainSize, SplitBy, Strategy, ToBbox};
+use common::{collection, feature};
+
+
+#[test]
+fn short_position_path_through_collection() {
+    let gc = Value::GeometryCollection(vec![
+        Geometry::new(Value::Point(vec![0.0, 0.0])),
+        Geometry::new(Value::Polygon(vec![
+            vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![1.0], vec![0.0, 0.0]],
+        ])),
+    ]);
+    let fc = collection(vec![feature(Some(Geometry::new(gc)))]);
+    let err = GeoJson::FeatureCollection(fc).to_bbox().unwrap_err();
+    assert_eq!(err.kind(), BboxErrorKind::ShortPosition(1));
+    assert_eq!(err.path().to_string(),
+               "features[0].geometry.geometries[1].coordinates[0][2]");
+    assert_eq!(err.to_string(),
+               "position has 1 element(s), expected at least 2 at \
+                features[0].geometry.geometries[1].coordinates[0][2]");
+}
+
+
+#[test]
+fn short_position_in_multi_polygon() {
+    let mp = Value::MultiPolygon(vec![
+        vec![vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![0.0, 1.0], vec![0.0, 0.0]]],
+        vec![vec![vec![2.0, 2.0], vec![]]],
+    ]);
+    let err = mp.to_bbox().unwrap_err();
+    assert_eq!(err.kind(), BboxErrorKind::ShortPosition(0));
+    assert_eq!(err.path().to_string(), "coordinates[1][0][1]");
+}
+
+
+#[test]
+fn lowest_index_error_wins() {
+    let fc = collection((0..100).map(|i| {
+        let point = if i % 7 == 3 { vec![0.0] } else { vec![0.0, 0.0] };
+        feature(Some(Geometry::new(Value::Point(point))))
+    }).collect());
+    for &grain_size in &[GrainSize::Adaptive, GrainSize::Fixed(1), GrainSize::Fixed(8)] {
+        for &split_by in &[SplitBy::Elements, SplitBy::Vertices] {
+            for &strategy in &[Strategy::Join, Strategy::Reduce, Strategy::Sequential] {
+                let options = BboxOptions::default().with_strategy(strategy).with_grain_size(grain_size)
+                    .with_split_by(split_by);
+                let err = fc.to_bbox_with(&options).unwrap_err();
+                assert_eq!(err.path().to_string(), "features[3].geometry.coordinates");
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+extern crate geojson;
+extern crate par_bbox;
+
+mod common;
+
+use geojson::{Feature, FeatureCollection, Geometry, Value};
+use par_bbox::{Bbox, FeatureKey, ToBbox, feature_bboxes, write_csv, write_ndjson};
+use common::polys_collection;
+
+
+#[test]
+fn per_feature_bboxes_keyed_by_property() {
+    let fc = polys_collection();
+    let rows = feature_bboxes(&fc, &FeatureKey::Property("osm_id".to_string())).unwrap();
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].index, 0);
+    assert_eq!(rows[0].id, Some("214061298.0".to_string()));
+    assert_eq!(rows[1].bbox, fc.features[1].to_bbox().unwrap());
+
+    let total = rows.iter().fold(Bbox::empty(), |acc, row| acc.merge(&row.bbox));
+    assert_eq!(total, fc.to_bbox().unwrap());
+}
+
+
+#[test]
+fn csv_and_ndjson_rows() {
+    let feature = |id: &str, geometry: Option<Geometry>| Feature {
+        bbox: None,
+        geometry,
+        id: Some(id.into()),
+        properties: None,
+        foreign_members: None,
+    };
+    let fc = FeatureCollection {
+        bbox: None,
+        features: vec![
+            feature("a,b", Some(Geometry::new(Value::Point(vec![1.0, 2.0])))),
+            feature("empty", None),
+        ],
+        foreign_members: None,
+    };
+    let rows = feature_bboxes(&fc, &FeatureKey::Id).unwrap();
+
+    let mut csv = Vec::new();
+    write_csv(&rows, &mut csv).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap(),
+               "index,id,xmin,ymin,xmax,ymax\n0,\"a,b\",1,2,1,2\n1,empty,,,,\n");
+
+    let mut ndjson = Vec::new();
+    write_ndjson(&rows, &mut ndjson).unwrap();
+    let lines: Vec<String> = String::from_utf8(ndjson).unwrap().lines().map(String::from).collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("\"id\":\"a,b\""));
+    assert!(lines[1].contains("\"xmin\":null"));
+}
@@ -0,0 +1,239 @@
+extern crate geojson;
+extern crate par_bbox;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use std::convert::TryInto;
+
+use geojson::{Feature, FeatureCollection, GeoJson, Geometry, Value};
+use par_bbox::{Bbox, ReadError, ToBbox, check_bbox_members, parse_flatgeobuf, write_flatgeobuf};
+use common::collection;
+
+
+fn feature(value: Option<Value>, properties: serde_json::Value) -> Feature {
+    Feature { properties: properties.as_object().cloned(), ..common::feature(value.map(Geometry::new)) }
+}
+
+
+fn write(fc: &FeatureCollection) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_flatgeobuf(fc, &mut out).unwrap();
+    out
+}
+
+
+fn square(x: f64, y: f64, size: f64) -> Vec<Vec<f64>> {
+    vec![vec![x, y], vec![x + size, y], vec![x + size, y + size], vec![x, y + size], vec![x, y]]
+}
+
+
+// The nodes of the index, as (xmin, ymin, xmax, ymax, offset), and where the
+// features start.
+fn index(data: &[u8], num_nodes: usize) -> (Vec<([f64; 4], u64)>, usize) {
+    let header_len = u32::from_le_bytes(data[8..12].try_into().unwrap()) as usize;
+    let start = 12 + header_len;
+    let nodes = data[start..start + 40 * num_nodes].chunks(40)
+        .map(|node| {
+            let f = |i: usize| f64::from_le_bytes(node[8 * i..8 * i + 8].try_into().unwrap());
+            ([f(0), f(1), f(2), f(3)], u64::from_le_bytes(node[32..].try_into().unwrap()))
+        })
+        .collect();
+    (nodes, start + 40 * num_nodes)
+}
+
+
+#[test]
+fn round_trips_geometries_and_properties() {
+    let hole = vec![vec![1.0, 1.0], vec![2.0, 1.0], vec![2.0, 2.0], vec![1.0, 1.0]];
+    let fc = collection(vec![
+        feature(Some(Value::Polygon(vec![square(0.0, 0.0, 4.0), hole])),
+                json!({"name": "square", "pop": 120, "area": 16.0, "open": true})),
+        feature(Some(Value::MultiPolygon(vec![vec![square(10.0, 0.0, 1.0)], vec![square(20.0, 5.0, 2.0)]])),
+                json!({"name": "islands", "pop": 7, "area": 2.5, "tags": ["a", "b"]})),
+        feature(Some(Value::Point(vec![-3.0, 2.0, 8.0])), json!({"name": null})),
+        feature(Some(Value::MultiLineString(vec![vec![vec![0.0, -1.0], vec![1.0, -2.0]],
+                                                 vec![vec![5.0, 5.0], vec![6.0, 6.0]]])),
+                json!({})),
+        feature(Some(Value::GeometryCollection(vec![Geometry::new(Value::LineString(vec![vec![0.0, 0.0],
+                                                                                        vec![1.0, 1.0]])),
+                                                    Geometry::new(Value::MultiPoint(vec![]))])),
+                json!({"pop": 1.5})),
+        feature(None, json!({"name": "nowhere"})),
+    ]);
+    let read = parse_flatgeobuf(&write(&fc)).unwrap();
+    assert_eq!(read.features.len(), fc.features.len());
+
+    // Features are reordered along the Hilbert curve, so match them up by
+    // geometry.
+    for original in &fc.features {
+        let copy = read.features.iter().find(|f| f.geometry == original.geometry)
+            .unwrap_or_else(|| panic!("{:?} was not read back", original.geometry));
+        let mut expected: serde_json::Map<String, serde_json::Value> = original.properties.clone().unwrap()
+            .into_iter()
+            .filter(|(_, v)| !v.is_null())
+            .collect();
+        // "pop" is a mix of integers and other numbers, so it is a Double.
+        if let Some(pop) = expected.get_mut("pop") {
+            *pop = json!(pop.as_f64().unwrap());
+        }
+        assert_eq!(copy.properties.clone().unwrap_or_default(), expected);
+    }
+    assert_eq!(read.to_bbox().unwrap(), Bbox::new_3d(-3.0, -2.0, 8.0, 22.0, 7.0, 8.0));
+}
+
+
+#[test]
+fn header_and_index_are_bbox_members() {
+    let fc = collection(vec![
+        feature(Some(Value::Polygon(vec![square(0.0, 0.0, 2.0)])), json!({})),
+        feature(Some(Value::Point(vec![10.0, 4.0])), json!({})),
+        feature(None, json!({})),
+    ]);
+    let read = parse_flatgeobuf(&write(&fc)).unwrap();
+    assert_eq!(read.bbox, Some(vec![0.0, 0.0, 10.0, 4.0]));
+    let point = read.features.iter().find(|f| f.geometry.is_some() && f.bbox == Some(vec![10.0, 4.0, 10.0, 4.0]));
+    assert!(point.is_some());
+    assert!(read.features.iter().any(|f| f.geometry.is_none() && f.bbox.is_none()));
+
+    let report = check_bbox_members(&GeoJson::FeatureCollection(read)).unwrap();
+    assert_eq!(report.checked, 3);
+    assert!(report.mismatches.is_empty());
+}
+
+
+#[test]
+fn index_is_a_packed_hilbert_r_tree() {
+    // 40 features in a 5x8 grid, written in row order.
+    let features = (0..40)
+        .map(|i| feature(Some(Value::Point(vec![(i % 5) as f64, (i / 5) as f64])), json!({"i": i})))
+        .collect();
+    let data = write(&collection(features));
+
+    // 40 leaves, 3 nodes above them and the root.
+    let (nodes, features_start) = index(&data, 44);
+    assert_eq!(nodes[0].0, [0.0, 0.0, 4.0, 7.0]);
+    assert_eq!(nodes[0].1, 1);
+    for (i, node) in nodes[1..4].iter().enumerate() {
+        let first = node.1 as usize;
+        assert_eq!(first, 4 + 16 * i);
+        let children = &nodes[first..(first + 16).min(44)];
+        let union = children.iter().fold([f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY],
+                                         |b, c| [b[0].min(c.0[0]), b[1].min(c.0[1]), b[2].max(c.0[2]), b[3].max(c.0[3])]);
+        assert_eq!(node.0, union);
+    }
+
+    // The leaves point at the features in the order they were written, which
+    // keeps neighbours together rather than following the rows.
+    let read = parse_flatgeobuf(&data).unwrap();
+    let mut offset = 0;
+    for (leaf, feature) in nodes[4..].iter().zip(&read.features) {
+        assert_eq!(leaf.1, offset);
+        let len = u32::from_le_bytes(data[features_start + offset as usize..][..4].try_into().unwrap());
+        offset += 4 + len as u64;
+        let position = match feature.geometry.as_ref().unwrap().value {
+            Value::Point(ref p) => p.clone(),
+            ref other => panic!("expected a Point, got {:?}", other),
+        };
+        assert_eq!(leaf.0, [position[0], position[1], position[0], position[1]]);
+    }
+    assert_eq!(features_start + offset as usize, data.len());
+    let order: Vec<i64> = read.features.iter().map(|f| f.properties.as_ref().unwrap()["i"].as_i64().unwrap()).collect();
+    assert_ne!(order, (0..40).collect::<Vec<i64>>());
+    assert_ne!(order, (0..40).rev().collect::<Vec<i64>>());
+}
+
+
+#[test]
+fn features_without_coordinates_are_indexed_last() {
+    let fc = collection(vec![
+        feature(None, json!({"i": 0})),
+        feature(Some(Value::Point(vec![1.0, 2.0])), json!({"i": 1})),
+        feature(Some(Value::MultiPolygon(vec![])), json!({"i": 2})),
+        feature(Some(Value::Point(vec![3.0, -1.0])), json!({"i": 3})),
+    ]);
+    let data = write(&fc);
+    let (nodes, _) = index(&data, 5);
+    assert_eq!(nodes[0].0, [1.0, -1.0, 3.0, 2.0]);
+    let empty = [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY];
+    assert!(nodes[1..3].iter().all(|node| node.0 != empty));
+    assert_eq!(nodes[3].0, empty);
+    assert_eq!(nodes[4].0, empty);
+
+    let read = parse_flatgeobuf(&data).unwrap();
+    let order: Vec<i64> = read.features.iter().map(|f| f.properties.as_ref().unwrap()["i"].as_i64().unwrap()).collect();
+    assert_eq!(order[2..], [0, 2]);
+    assert!(read.features[2..].iter().all(|f| f.bbox.is_none()));
+}
+
+
+#[test]
+fn short_position_in_a_hole_is_an_error() {
+    let polygon = Value::Polygon(vec![square(0.0, 0.0, 2.0), vec![vec![0.5]]]);
+    let fc = collection(vec![
+        feature(Some(Value::Point(vec![0.0, 0.0])), json!({})),
+        feature(Some(Value::GeometryCollection(vec![Geometry::new(polygon)])), json!({})),
+    ]);
+    match write_flatgeobuf(&fc, Vec::new()) {
+        Err(ReadError::Bbox(e)) => {
+            assert_eq!(e.to_string(), "position has 1 element(s), expected at least 2 at \
+                                       features[1].geometry.geometries[0].coordinates[1][0]");
+        }
+        other => panic!("expected a bbox error, got {:?}", other),
+    }
+}
+
+
+#[test]
+fn stale_index_is_reported() {
+    let fc = collection(vec![feature(Some(Value::Polygon(vec![square(0.0, 0.0, 2.0)])), json!({}))]);
+    let mut data = write(&fc);
+    // Widen the leaf, the second of the two nodes.
+    let (_, features_start) = index(&data, 2);
+    let leaf_xmax = features_start - 40 + 16;
+    data[leaf_xmax..leaf_xmax + 8].copy_from_slice(&5f64.to_le_bytes());
+
+    let read = parse_flatgeobuf(&data).unwrap();
+    let report = check_bbox_members(&GeoJson::FeatureCollection(read)).unwrap();
+    assert_eq!(report.mismatches.len(), 1);
+    assert_eq!(report.mismatches[0].path.to_string(), "features[0].bbox");
+    assert_eq!(report.mismatches[0].difference, 3.0);
+}
+
+
+#[test]
+fn empty_collection() {
+    let read = parse_flatgeobuf(&write(&collection(vec![]))).unwrap();
+    assert!(read.features.is_empty());
+    assert_eq!(read.bbox, None);
+    assert!(read.to_bbox().unwrap().is_empty());
+}
+
+
+#[test]
+fn corrupt_input_is_an_error() {
+    let fc = collection(vec![
+        feature(Some(Value::Point(vec![0.0, 0.0])), json!({})),
+        feature(Some(Value::Point(vec![1.0, 1.0])), json!({})),
+    ]);
+    let data = write(&fc);
+    match parse_flatgeobuf(&data[..data.len() - 4]) {
+        Err(ReadError::Record(2, _)) => (),
+        other => panic!("expected an error in record 2, got {:?}", other),
+    }
+
+    let mut data = write(&collection(vec![feature(Some(Value::Point(vec![0.0, 0.0])), json!({"tags": ["a"]}))]));
+    let at = data.windows(5).position(|w| w == b"[\"a\"]").unwrap();
+    data[at + 4] = b'}';
+    match parse_flatgeobuf(&data) {
+        Err(ReadError::Record(1, ref e)) => {
+            assert!(e.to_string().starts_with("invalid FlatGeobuf: column tags is not valid JSON: "))
+        }
+        other => panic!("expected an error in record 1, got {:?}", other),
+    }
+
+    let e = parse_flatgeobuf(b"{\"type\": \"Point\"}").unwrap_err();
+    assert_eq!(e.to_string(), "invalid FlatGeobuf: not a FlatGeobuf file");
+    assert!(parse_flatgeobuf(&data[..20]).is_err());
+}
@@ -0,0 +1,70 @@
+extern crate par_bbox;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use par_bbox::{InputFormat, expand_input};
+
+
+// A fresh directory holding empty files at the given relative paths.
+fn tree(name: &str, files: &[&str]) -> PathBuf {
+    let root = env::temp_dir().join(format!("par_bbox_{}_{}", name, process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for file in files {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+    }
+    root
+}
+
+
+fn names(root: &Path, files: &[&str]) -> Vec<String> {
+    files.iter().map(|f| root.join(f).to_string_lossy().into_owned()).collect()
+}
+
+
+#[test]
+fn formats_from_extensions() {
+    assert_eq!(InputFormat::from_extension("a/b.geojson"), Some(InputFormat::GeoJson));
+    assert_eq!(InputFormat::from_extension("b.ndjson.gz"), Some(InputFormat::Sequence));
+    assert_eq!(InputFormat::from_extension("b.SHP"), Some(InputFormat::Shapefile));
+    assert_eq!(InputFormat::from_extension("b.Fgb.GZ"), Some(InputFormat::FlatGeobuf));
+    assert_eq!(InputFormat::from_extension("b.fgb.zst"), Some(InputFormat::FlatGeobuf));
+    assert_eq!(InputFormat::from_extension("b.gz"), None);
+    assert_eq!(InputFormat::from_extension("-"), None);
+}
+
+
+#[test]
+fn stdin_and_files_are_kept() {
+    assert_eq!(expand_input("-").unwrap(), vec!["-"]);
+    assert_eq!(expand_input("data/polys.geojson").unwrap(), vec!["data/polys.geojson"]);
+    // Opening the file reports that it doesn't exist.
+    assert_eq!(expand_input("no/such/file.geojson").unwrap(), vec!["no/such/file.geojson"]);
+}
+
+
+#[test]
+fn directories_are_searched_recursively() {
+    let files = ["b.geojson", "a/c.shp", "a/c.dbf", "a/d/e.ndjson.gz", "points.csv", "notes.txt", "f.fgb",
+                 "G.SHP", "POINTS.CSV"];
+    let root = tree("directory", &files);
+    let found = expand_input(root.to_str().unwrap()).unwrap();
+    assert_eq!(found, names(&root, &["G.SHP", "a/c.shp", "a/d/e.ndjson.gz", "b.geojson", "f.fgb"]));
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+#[test]
+fn globs_match_files_only() {
+    let root = tree("glob", &["x1.geojson", "x2.geojson", "x3.geojson/inner.geojson", "y.geojson", "x.csv"]);
+    let pattern = root.join("x*").to_string_lossy().into_owned();
+    assert_eq!(expand_input(&pattern).unwrap(), names(&root, &["x.csv", "x1.geojson", "x2.geojson"]));
+
+    let pattern = root.join("z*.geojson").to_string_lossy().into_owned();
+    assert_eq!(expand_input(&pattern).unwrap(), vec![pattern.clone()]);
+    fs::remove_dir_all(root).unwrap();
+}
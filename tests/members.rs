@@ -0,0 +1,95 @@
+extern crate geojson;
+extern crate par_bbox;
+
+mod common;
+
+use geojson::{GeoJson, Value};
+use par_bbox::{MemberOptions, ToBbox, fill_bbox_members};
+use common::load_polys;
+
+
+#[test]
+fn fills_collection_and_feature_members() {
+    let mut geojson = load_polys();
+    fill_bbox_members(&mut geojson, &MemberOptions::default()).unwrap();
+
+    let fc = match geojson {
+        GeoJson::FeatureCollection(ref fc) => fc,
+        _ => panic!("expected a FeatureCollection"),
+    };
+    assert_eq!(fc.bbox, Some(vec![-71.1906871, 42.228073, -71.1894741, 42.2285172]));
+    for feature in &fc.features {
+        assert_eq!(feature.bbox, Some(feature.to_bbox().unwrap().to_vec()));
+        assert_eq!(feature.geometry.as_ref().unwrap().bbox, None);
+    }
+
+    // The members survive a round trip through the serialized output.
+    let reparsed: GeoJson = geojson.to_string().parse().unwrap();
+    match reparsed {
+        GeoJson::FeatureCollection(ref fc2) => assert_eq!(fc2.bbox, fc.bbox),
+        _ => panic!("expected a FeatureCollection"),
+    }
+}
+
+
+#[test]
+fn fills_geometry_members_inside_collections() {
+    let mut geojson: GeoJson = r#"{
+        "type": "Feature",
+        "properties": {},
+        "geometry": {"type": "GeometryCollection", "geometries": [
+            {"type": "Point", "coordinates": [1.0, 2.0, 3.0]},
+            {"type": "LineString", "coordinates": []}
+        ]}
+    }"#.parse().unwrap();
+    let options = MemberOptions { geometries: true, antimeridian: false };
+    fill_bbox_members(&mut geojson, &options).unwrap();
+
+    let feature = match geojson {
+        GeoJson::Feature(feature) => feature,
+        _ => panic!("expected a Feature"),
+    };
+    assert_eq!(feature.bbox, Some(vec![1.0, 2.0, 3.0, 1.0, 2.0, 3.0]));
+    let geometry = feature.geometry.unwrap();
+    assert_eq!(geometry.bbox, feature.bbox);
+    match geometry.value {
+        Value::GeometryCollection(ref geoms) => {
+            assert_eq!(geoms[0].bbox, feature.bbox);
+            assert_eq!(geoms[1].bbox, None);
+        }
+        _ => panic!("expected a GeometryCollection"),
+    }
+}
+
+
+#[test]
+fn stale_geometry_members_are_removed() {
+    let text = r#"{
+        "type": "Feature",
+        "properties": {},
+        "geometry": {"type": "GeometryCollection", "bbox": [0, 0, 0, 0], "geometries": [
+            {"type": "Point", "bbox": [5, 5, 5, 5], "coordinates": [1.0, 2.0]}
+        ]}
+    }"#;
+    let mut geojson: GeoJson = text.parse().unwrap();
+    fill_bbox_members(&mut geojson, &MemberOptions::default()).unwrap();
+    let feature = match geojson {
+        GeoJson::Feature(feature) => feature,
+        _ => panic!("expected a Feature"),
+    };
+    assert_eq!(feature.bbox, Some(vec![1.0, 2.0, 1.0, 2.0]));
+    let geometry = feature.geometry.unwrap();
+    assert_eq!(geometry.bbox, None);
+    match geometry.value {
+        Value::GeometryCollection(ref geoms) => assert_eq!(geoms[0].bbox, None),
+        _ => panic!("expected a GeometryCollection"),
+    }
+
+    // A Geometry at the root still gets its own.
+    let mut geojson: GeoJson = r#"{"type": "Point", "bbox": [5, 5, 5, 5], "coordinates": [1.0, 2.0]}"#.parse().unwrap();
+    fill_bbox_members(&mut geojson, &MemberOptions::default()).unwrap();
+    match geojson {
+        GeoJson::Geometry(ref geometry) => assert_eq!(geometry.bbox, Some(vec![1.0, 2.0, 1.0, 2.0])),
+        _ => panic!("expected a Geometry"),
+    }
+}
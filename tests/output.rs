@@ -0,0 +1,48 @@
+extern crate par_bbox;
+
+use par_bbox::{Bbox, BboxFormat, write_bbox};
+
+
+fn written(bbox: &Bbox, format: BboxFormat) -> String {
+    let mut out = Vec::new();
+    write_bbox(bbox, format, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+
+#[test]
+fn formats() {
+    let bbox = Bbox::new(-1.5, 2.0, 3.0, 4.0);
+    assert_eq!(written(&bbox, BboxFormat::Json), "[-1.5,2.0,3.0,4.0]\n");
+    assert_eq!(written(&bbox, BboxFormat::Wkt),
+               "POLYGON ((-1.5 2, 3 2, 3 4, -1.5 4, -1.5 2))\n");
+    assert_eq!(written(&bbox, BboxFormat::Csv), "xmin,ymin,xmax,ymax\n-1.5,2,3,4\n");
+    assert_eq!(written(&bbox, BboxFormat::Gdal), "-te -1.5 2 3 4\n");
+    assert_eq!(written(&bbox, BboxFormat::GeoJson),
+               "{\"bbox\":[-1.5,2.0,3.0,4.0],\"geometry\":{\"coordinates\":\
+                [[[-1.5,2.0],[3.0,2.0],[3.0,4.0],[-1.5,4.0],[-1.5,2.0]]],\"type\":\"Polygon\"},\
+                \"properties\":{},\"type\":\"Feature\"}\n");
+}
+
+
+#[test]
+fn antimeridian_polygons_are_split() {
+    let bbox = Bbox::new(170.0, -10.0, -170.0, 10.0);
+    assert_eq!(written(&bbox, BboxFormat::Wkt),
+               "MULTIPOLYGON (((170 -10, 180 -10, 180 10, 170 10, 170 -10)), \
+                ((-180 -10, -170 -10, -170 10, -180 10, -180 -10)))\n");
+    assert_eq!(written(&bbox, BboxFormat::Json), "[170.0,-10.0,-170.0,10.0]\n");
+}
+
+
+#[test]
+fn empty_and_3d() {
+    let empty = Bbox::empty();
+    assert_eq!(written(&empty, BboxFormat::Json), "null\n");
+    assert_eq!(written(&empty, BboxFormat::Wkt), "POLYGON EMPTY\n");
+    assert_eq!(written(&empty, BboxFormat::Gdal), "");
+
+    let bbox = Bbox::new_3d(0.0, 1.0, 2.0, 3.0, 4.0, 5.0);
+    assert_eq!(written(&bbox, BboxFormat::Json), "[0.0,1.0,2.0,3.0,4.0,5.0]\n");
+    assert_eq!(written(&bbox, BboxFormat::Csv), "xmin,ymin,xmax,ymax,zmin,zmax\n0,1,3,4,2,5\n");
+}
@@ -0,0 +1,156 @@
+extern crate geojson;
+extern crate par_bbox;
+
+mod common;
+
+use std::sync::Mutex;
+
+use geojson::{GeoJson, Geometry, Value};
+use par_bbox::{Bbox, BboxError, BboxErrorKind, BboxOptions, GrainSize, SplitBy, Strategy, ToBbox, compute_bbox,
+               compute_bbox_weighted};
+use common::load_polys;
+
+
+#[test]
+fn feature_collection_bbox() {
+    let bbox = load_polys().to_bbox().unwrap();
+    assert_eq!(bbox, Bbox::new(-71.1906871, 42.228073, -71.1894741, 42.2285172));
+}
+
+
+#[test]
+fn feature_bboxes_merge_to_total() {
+    let fc = match load_polys() {
+        GeoJson::FeatureCollection(fc) => fc,
+        _ => panic!("expected a FeatureCollection"),
+    };
+    assert_eq!(fc.features.len(), 3);
+
+    let merged = fc.features.iter()
+        .map(|f| f.to_bbox().unwrap())
+        .fold(None, |acc: Option<Bbox>, b| Some(acc.map_or(b, |a| a.merge(&b))))
+        .unwrap();
+    assert_eq!(merged, fc.to_bbox().unwrap());
+}
+
+
+#[test]
+fn polygon_uses_outer_ring() {
+    let polygon = Value::Polygon(vec![
+        vec![vec![0.0, 0.0], vec![4.0, 0.0], vec![4.0, 3.0], vec![0.0, 0.0]],
+        vec![vec![9.0, 9.0], vec![9.5, 9.0], vec![9.0, 9.5], vec![9.0, 9.0]],
+    ]);
+    assert_eq!(polygon.to_bbox().unwrap(), Bbox::new(0.0, 0.0, 4.0, 3.0));
+}
+
+
+#[test]
+fn multi_line_string_uses_every_line() {
+    let mls = Value::MultiLineString(vec![
+        vec![vec![0.0, 0.0], vec![1.0, 1.0]],
+        vec![vec![-2.0, 5.0], vec![-1.0, 6.0]],
+    ]);
+    assert_eq!(mls.to_bbox().unwrap(), Bbox::new(-2.0, 0.0, 1.0, 6.0));
+}
+
+
+#[test]
+fn geometry_collection_merges_members() {
+    let gc = Geometry::new(Value::GeometryCollection(vec![
+        Geometry::new(Value::Point(vec![10.0, -10.0])),
+        Geometry::new(Value::LineString(vec![vec![-3.0, 2.0], vec![0.0, 0.0]])),
+    ]));
+    assert_eq!(gc.to_bbox().unwrap(), Bbox::new(-3.0, -10.0, 10.0, 2.0));
+}
+
+
+#[test]
+fn compute_bbox_with_custom_func() {
+    let points = vec![(1.0, 2.0), (-1.0, 0.5), (3.0, -4.0)];
+    let bbox = compute_bbox(&points, &|&(x, y): &(f64, f64)| Ok(Bbox::new(x, y, x, y))).unwrap();
+    assert_eq!(bbox, Bbox::new(-1.0, -4.0, 3.0, 2.0));
+}
+
+
+#[test]
+fn grain_size_does_not_change_result() {
+    let line = Value::LineString((0..5000).map(|i| {
+        let t = i as f64 * 0.01;
+        vec![t.cos() * t, t.sin() * t]
+    }).collect());
+    let expected = line.to_bbox().unwrap();
+    for &grain_size in &[GrainSize::Fixed(1), GrainSize::Fixed(7), GrainSize::Fixed(10000)] {
+        let options = BboxOptions::default().with_grain_size(grain_size);
+        assert_eq!(line.to_bbox_with(&options).unwrap(), expected);
+    }
+}
+
+
+#[test]
+fn weighted_split_matches_element_split() {
+    // One heavy element among many light ones, at every grain size.
+    let mut values: Vec<Vec<(f64, f64)>> = (0..50).map(|i| vec![(i as f64, -(i as f64))]).collect();
+    values.insert(20, (0..1000).map(|i| (i as f64 * 0.5, i as f64)).collect());
+    let func = |points: &Vec<(f64, f64)>| {
+        Ok(points.iter().fold(Bbox::empty(), |b, &(x, y)| b.merge(&Bbox::from_point(x, y))))
+    };
+    let expected = Bbox::new(0.0, -49.0, 499.5, 999.0);
+    for &grain_size in &[GrainSize::Adaptive, GrainSize::Fixed(1), GrainSize::Fixed(100)] {
+        for &split_by in &[SplitBy::Elements, SplitBy::Vertices] {
+            let options = BboxOptions::default().with_grain_size(grain_size).with_split_by(split_by);
+            let bbox = compute_bbox_weighted(&values, &options, &|v| v.len(), &func).unwrap();
+            assert_eq!(bbox, expected);
+        }
+    }
+}
+
+
+// The index of the first element of every slice folded sequentially. Every
+// element fails, so each slice stops at its first element while the others
+// carry on.
+fn slice_starts(vertices: &[usize], options: &BboxOptions) -> Vec<usize> {
+    let values: Vec<(usize, usize)> = vertices.iter().cloned().enumerate().collect();
+    let starts = Mutex::new(Vec::new());
+    let result = compute_bbox_weighted(&values, options, &|&(_, n)| n, &|&(i, _)| {
+        starts.lock().unwrap().push(i);
+        Err(BboxError::new(BboxErrorKind::ShortPosition(0)))
+    });
+    assert_eq!(result.unwrap_err().path().to_string(), "[0]");
+    let mut starts = starts.into_inner().unwrap();
+    starts.sort();
+    starts
+}
+
+
+#[test]
+fn vertex_split_isolates_heavy_elements() {
+    // A 999-vertex element followed by 999 empty ones weighs 1000 + 999, so
+    // halving the weight splits right after the heavy element, where halving
+    // the count splits in the middle.
+    let mut vertices = vec![999];
+    vertices.extend(vec![0; 999]);
+    let options = BboxOptions::default().with_grain_size(GrainSize::Fixed(999));
+    assert_eq!(slice_starts(&vertices, &options.with_split_by(SplitBy::Vertices)), vec![0, 1]);
+    assert_eq!(slice_starts(&vertices, &options.with_split_by(SplitBy::Elements)), vec![0, 500]);
+
+    // A heavy element in the middle ends up in a slice of its own.
+    let mut vertices = vec![1; 100];
+    vertices[60] = 10_000;
+    let options = options.with_grain_size(GrainSize::Fixed(200)).with_split_by(SplitBy::Vertices);
+    let starts = slice_starts(&vertices, &options);
+    let heavy = starts.iter().position(|&i| i == 60).unwrap();
+    assert_eq!(starts[heavy + 1], 61);
+}
+
+
+#[test]
+fn strategies_agree() {
+    let geojson = load_polys();
+    let expected = geojson.to_bbox().unwrap();
+    for &strategy in &[Strategy::Join, Strategy::Reduce, Strategy::Sequential] {
+        for &grain_size in &[GrainSize::Adaptive, GrainSize::Fixed(1)] {
+            let options = BboxOptions::default().with_strategy(strategy).with_grain_size(grain_size);
+            assert_eq!(geojson.to_bbox_with(&options).unwrap(), expected);
+        }
+    }
+}
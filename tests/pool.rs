@@ -0,0 +1,40 @@
+extern crate geojson;
+extern crate par_bbox;
+
+mod common;
+
+use std::thread;
+
+use par_bbox::{Bbox, BboxError, BboxOptions, ToBbox, compute_bbox_with, thread_pool};
+use common::load_polys;
+
+
+#[test]
+fn pool_matches_global() {
+    let geojson = load_polys();
+
+    let options = BboxOptions::default();
+    assert_eq!(geojson.to_bbox_in(&thread_pool(2).unwrap(), &options).unwrap(), geojson.to_bbox().unwrap());
+}
+
+
+// Points whose bbox may only be computed on a pool's worker threads, which
+// the main test thread is not.
+struct OnPool(Vec<(f64, f64)>, thread::ThreadId);
+
+impl ToBbox for OnPool {
+    fn to_bbox_with(&self, options: &BboxOptions) -> Result<Bbox, BboxError> {
+        compute_bbox_with(&self.0, options, &|&(x, y)| {
+            assert!(thread::current().id() != self.1, "ran on the calling thread");
+            Ok(Bbox::from_point(x, y))
+        })
+    }
+}
+
+
+#[test]
+fn work_runs_on_the_given_pool() {
+    let points = OnPool((0..1000).map(|i| (i as f64, -(i as f64))).collect(), thread::current().id());
+    let bbox = points.to_bbox_in(&thread_pool(2).unwrap(), &BboxOptions::default()).unwrap();
+    assert_eq!(bbox, Bbox::new(0.0, -999.0, 999.0, 0.0));
+}
@@ -0,0 +1,157 @@
+extern crate geojson;
+extern crate par_bbox;
+extern crate serde_json;
+
+mod common;
+
+use geojson::GeoJson;
+use par_bbox::{ReadError, Skipped, ToBbox, par_scan_bbox, scan_bbox};
+use common::polys_text;
+
+
+// Scanning must give exactly what parsing and to_bbox give: the same bbox
+// and skipped counts, or an error at the same path.
+fn assert_same_as_dom(data: &str) {
+    assert_scan_matches_dom(data, scan_bbox);
+    assert_scan_matches_dom(data, par_scan_bbox);
+}
+
+
+fn assert_scan_matches_dom(data: &str, scan: fn(&[u8]) -> Result<par_bbox::Streamed, ReadError>) {
+    let geojson: GeoJson = data.parse().unwrap();
+    let scanned = scan(data.as_bytes());
+    match geojson.to_bbox() {
+        Ok(bbox) => {
+            let scanned = scanned.unwrap();
+            assert_eq!(scanned.bbox, bbox, "{}", data);
+            assert_eq!(scanned.skipped, Skipped::count(&geojson), "{}", data);
+        }
+        Err(e) => match scanned {
+            Err(ReadError::Bbox(scan_e)) => assert_eq!(scan_e, e, "{}", data),
+            other => panic!("expected {:?}, got {:?}", e, other),
+        },
+    }
+}
+
+
+#[test]
+fn scan_matches_dom_on_polys() {
+    assert_same_as_dom(&polys_text());
+}
+
+
+#[test]
+fn scan_matches_dom_for_every_geometry_type() {
+    let geometries = [
+        r#"{"type": "Point", "coordinates": [1, 2, 3]}"#,
+        r#"{"type": "MultiPoint", "coordinates": [[1, 2], [-3, 4, 5]]}"#,
+        r#"{"type": "LineString", "coordinates": [[179, 0], [-179, 1]]}"#,
+        r#"{"type": "MultiLineString", "coordinates": [[[0, 0], [1, 1]], [], [[-2, 5]]]}"#,
+        r#"{"type": "Polygon", "coordinates": [[[0, 0], [4, 0], [4, 3], [0, 0]], [[9, 9], [9, 9.5], [9.5, 9], [9, 9]]]}"#,
+        r#"{"type": "Polygon", "coordinates": []}"#,
+        r#"{"type": "MultiPolygon", "coordinates": [[[[0, 0], [1, 0], [0, 1], [0, 0]], [[50, 50], [51, 50], [50, 51], [50, 50]]], [[[-5, -5], [-4, -5], [-5, -4], [-5, -5]]]]}"#,
+        r#"{"type": "GeometryCollection", "geometries": [{"type": "Point", "coordinates": [10, -10]}, {"type": "GeometryCollection", "geometries": []}]}"#,
+        // Coordinates before the type.
+        r#"{"coordinates": [[[0, 0], [2, 0], [0, 2], [0, 0]], [[7, 7], [8, 7], [7, 8], [7, 7]]], "type": "Polygon"}"#,
+    ];
+    for geometry in &geometries {
+        assert_same_as_dom(geometry);
+    }
+}
+
+
+#[test]
+fn scan_matches_dom_on_features() {
+    assert_same_as_dom(r#"{"type": "FeatureCollection", "bbox": [0, 0, 0, 0], "features": [
+        {"type": "Feature", "properties": {"coordinates": [[1000, 1000]]}, "geometry": null},
+        {"properties": {}, "geometry": {"type": "MultiPolygon", "coordinates": []}, "type": "Feature"},
+        {"type": "Feature", "id": 3, "geometry": {"type": "LineString", "coordinates": [[1, 2], [3, 4]]}, "properties": null}
+    ]}"#);
+    assert_same_as_dom(r#"{"type": "Feature", "properties": {}, "geometry": null}"#);
+}
+
+
+#[test]
+fn scan_error_paths_match_dom() {
+    assert_same_as_dom(r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [1, 2]}},
+        {"type": "Feature", "properties": {}, "geometry": {"type": "MultiPolygon", "coordinates": [[[[0, 0]]], [[[1, 1], [2]]]]}},
+        {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [1]}}
+    ]}"#);
+    // Only the outer ring of a polygon is read, so errors in holes don't count.
+    assert_same_as_dom(r#"{"type": "Polygon", "coordinates": [[[0, 0], [1, 1]], [[5]]]}"#);
+    assert_same_as_dom(r#"{"type": "GeometryCollection", "geometries": [{"type": "Point", "coordinates": []}]}"#);
+
+    match scan_bbox(br#"{"type": "FeatureCollection", "features": [}"#) {
+        Err(ReadError::Json(_)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+
+// A FeatureCollection big enough to be split into several chunks, with
+// brackets and escaped quotes in strings to throw off the splitting.
+fn many_features(bad_index: Option<usize>) -> String {
+    let features: Vec<String> = (0..3000)
+        .map(|i| {
+            let geometry = if Some(i) == bad_index {
+                r#"{"type": "Point", "coordinates": [1]}"#.to_string()
+            } else if i % 100 == 7 {
+                "null".to_string()
+            } else {
+                format!(r#"{{"type": "LineString", "coordinates": [[{}, {}], [{}, -{}]]}}"#,
+                        i, i % 90, -(i as f64) / 17.0, i % 45)
+            };
+            format!(r#"{{"type": "Feature", "properties": {{"name": "a \"]}}, [ {}", "features": []}}, "geometry": {}}}"#,
+                    i, geometry)
+        })
+        .collect();
+    format!("{{\"features\": [\n{}\n], \"type\": \"FeatureCollection\"}}", features.join(",\n"))
+}
+
+
+#[test]
+fn parallel_scan_matches_dom_across_chunks() {
+    assert_same_as_dom(&many_features(None));
+    assert_same_as_dom(&many_features(Some(2500)));
+
+    let data = many_features(None);
+    let broken = data.replacen("\"geometry\": null", "\"geometry\": nul", 20);
+    match par_scan_bbox(broken.as_bytes()) {
+        Err(ReadError::Json(_)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+
+#[test]
+fn truncated_input_is_an_error() {
+    let data = br#"{"type":"FeatureCollection","features":[{"a\"#;
+    for end in 1..data.len() + 1 {
+        for scan in &[scan_bbox, par_scan_bbox] {
+            match scan(&data[..end]) {
+                Err(ReadError::Truncated(_)) => (),
+                other => panic!("unexpected result {:?} for {:?}", other, &data[..end]),
+            }
+        }
+    }
+    let e = scan_bbox(br#"{"type": "Point", "coordinates": [1,"#).unwrap_err();
+    assert_eq!(e.to_string(), "input ends early, is it truncated? (EOF while parsing a value at line 1 column 36)");
+    assert_eq!(scan_bbox(b" \n").unwrap_err().to_string(), "input is empty");
+}
+
+
+#[test]
+fn invalid_utf8_is_an_error() {
+    let data = b"{\"type\": \"Feature\", \"properties\": {\"name\": \"caf\xe9\"}, \"geometry\": null}";
+    for scan in &[scan_bbox, par_scan_bbox] {
+        match scan(data) {
+            Err(ReadError::NotUtf8(47, _)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+    // The same explanation for errors from parsing the document.
+    let e = serde_json::from_slice::<GeoJson>(data).unwrap_err();
+    assert!(ReadError::from_json(data, e).to_string()
+                .starts_with("input is not UTF-8: invalid byte at offset 47 ("));
+}
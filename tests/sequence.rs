@@ -0,0 +1,81 @@
+extern crate geojson;
+extern crate par_bbox;
+
+mod common;
+
+use geojson::GeoJson;
+use par_bbox::{Bbox, ReadError, Skipped, ToBbox, sequence_bbox};
+use common::load_polys;
+
+
+#[test]
+fn ndjson_matches_in_memory() {
+    let geojson = load_polys();
+    let mut lines = String::new();
+    if let GeoJson::FeatureCollection(ref fc) = geojson {
+        for feature in &fc.features {
+            lines.push_str(&GeoJson::Feature(feature.clone()).to_string());
+            lines.push('\n');
+        }
+    }
+
+    for &chunk_size in &[1, 2, 3, 1000] {
+        let streamed = sequence_bbox(lines.as_bytes(), chunk_size).unwrap();
+        assert_eq!(streamed.bbox, geojson.to_bbox().unwrap());
+        assert_eq!(streamed.skipped, Skipped::default());
+    }
+}
+
+
+#[test]
+fn text_sequence_allows_multiline_records() {
+    let data = "\x1e{\"type\": \"Point\",\n \"coordinates\": [1, 2]}\n\
+                \x1e{\"type\": \"Feature\", \"properties\": {}, \"geometry\": null}\n\
+                \x1e{\"type\": \"LineString\",\n \"coordinates\": [[-3, 0], [4, 5]]}\n";
+    let streamed = sequence_bbox(data.as_bytes(), 2).unwrap();
+    assert_eq!(streamed.bbox, Bbox::new(-3.0, 0.0, 4.0, 5.0));
+    assert_eq!(streamed.skipped, Skipped { null_geometry: 1, empty_geometry: 0 });
+}
+
+
+#[test]
+fn errors_report_line_numbers() {
+    let data = "{\"type\": \"Point\", \"coordinates\": [1, 2]}\n\
+                \n\
+                {\"type\": \"Point\", \"coordinates\": [3, 4]}\n\
+                {\"type\": \"Point\", \"coordinates\": [5]}\n";
+    match sequence_bbox(data.as_bytes(), 1) {
+        Err(ReadError::Record(4, ref e)) => match **e {
+            ReadError::Bbox(ref e) => assert_eq!(e.path().to_string(), "coordinates"),
+            ref other => panic!("unexpected error {:?}", other),
+        },
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    match sequence_bbox(&b"{\"type\": \"Point\", \"coordinates\": [1, 2]}\n{oops\n"[..], 10) {
+        Err(e @ ReadError::Record(2, _)) => assert!(e.to_string().starts_with("record 2: ")),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+
+#[test]
+fn records_explain_non_utf8_and_truncated_input() {
+    let data = b"{\"type\": \"Point\", \"coordinates\": [1, 2]}\n{\"type\": \"Point\", \"coordinates\": [3,\n";
+    match sequence_bbox(&data[..], 10) {
+        Err(ReadError::Record(2, ref e)) => match **e {
+            ReadError::Truncated(_) => (),
+            ref other => panic!("unexpected error {:?}", other),
+        },
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let data = b"{\"type\": \"Point\", \"coordinates\": [1, 2]}\n{\"type\": \"Point\", \"caf\xe9\": 1}\n";
+    match sequence_bbox(&data[..], 10) {
+        Err(ReadError::Record(2, ref e)) => match **e {
+            ReadError::NotUtf8(22, _) => (),
+            ref other => panic!("unexpected error {:?}", other),
+        },
+        other => panic!("unexpected result {:?}", other),
+    }
+}
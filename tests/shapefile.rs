@@ -0,0 +1,263 @@
+extern crate geojson;
+extern crate par_bbox;
+
+use geojson::{GeoJson, Value};
+use par_bbox::{Bbox, ReadError, Skipped, ToBbox, check_bbox_members, parse_shapefile};
+
+
+fn le_f64s(out: &mut Vec<u8>, values: &[f64]) {
+    for v in values {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+}
+
+
+fn le_i32s(out: &mut Vec<u8>, values: &[i32]) {
+    for v in values {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+}
+
+
+fn extent(points: &[[f64; 2]]) -> [f64; 4] {
+    points.iter().fold([f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY], |b, p| {
+        [b[0].min(p[0]), b[1].min(p[1]), b[2].max(p[0]), b[3].max(p[1])]
+    })
+}
+
+
+// A Polygon (type 5) record's content.
+fn polygon(rings: &[&[[f64; 2]]]) -> Vec<u8> {
+    let points: Vec<[f64; 2]> = rings.iter().flat_map(|r| r.iter().cloned()).collect();
+    let mut content = Vec::new();
+    le_i32s(&mut content, &[5]);
+    le_f64s(&mut content, &extent(&points));
+    le_i32s(&mut content, &[rings.len() as i32, points.len() as i32]);
+    let mut start = 0;
+    for ring in rings {
+        le_i32s(&mut content, &[start]);
+        start += ring.len() as i32;
+    }
+    for p in &points {
+        le_f64s(&mut content, p);
+    }
+    content
+}
+
+
+// A PointZ (type 11) record's content, with an m value.
+fn point_z(x: f64, y: f64, z: f64) -> Vec<u8> {
+    let mut content = Vec::new();
+    le_i32s(&mut content, &[11]);
+    le_f64s(&mut content, &[x, y, z, 0.0]);
+    content
+}
+
+
+fn null_shape() -> Vec<u8> {
+    vec![0; 4]
+}
+
+
+// The .shp and .shx for these records, with the given header extent.
+fn shapefile(shape_type: i32, header: [f64; 8], records: &[Vec<u8>]) -> (Vec<u8>, Vec<u8>) {
+    let mut body = Vec::new();
+    let mut index = Vec::new();
+    for (i, content) in records.iter().enumerate() {
+        let offset = (100 + body.len()) / 2;
+        index.extend_from_slice(&(offset as i32).to_be_bytes());
+        index.extend_from_slice(&((content.len() / 2) as i32).to_be_bytes());
+        body.extend_from_slice(&(i as i32 + 1).to_be_bytes());
+        body.extend_from_slice(&((content.len() / 2) as i32).to_be_bytes());
+        body.extend_from_slice(content);
+    }
+    let file = |contents: &[u8]| {
+        let mut out = Vec::new();
+        out.extend_from_slice(&9994i32.to_be_bytes());
+        out.extend_from_slice(&[0; 20]);
+        out.extend_from_slice(&(((100 + contents.len()) / 2) as i32).to_be_bytes());
+        le_i32s(&mut out, &[1000, shape_type]);
+        le_f64s(&mut out, &header);
+        out.extend_from_slice(contents);
+        out
+    };
+    (file(&body), file(&index))
+}
+
+
+// A .dbf with a character field NAME and a numeric field POP.
+fn dbf(rows: &[(&str, &str)]) -> Vec<u8> {
+    let record_len = 1 + 10 + 8;
+    let mut out = vec![3, 124, 1, 1];
+    out.extend_from_slice(&(rows.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(32 + 2 * 32 + 1u16).to_le_bytes());
+    out.extend_from_slice(&(record_len as u16).to_le_bytes());
+    out.extend_from_slice(&[0; 20]);
+    for &(name, kind, len) in &[("NAME", b'C', 10u8), ("POP", b'N', 8)] {
+        let mut descriptor = vec![0; 32];
+        descriptor[..name.len()].copy_from_slice(name.as_bytes());
+        descriptor[11] = kind;
+        descriptor[16] = len;
+        out.extend(descriptor);
+    }
+    out.push(0x0d);
+    for &(name, pop) in rows {
+        out.push(b' ');
+        out.extend(format!("{:<10}{:>8}", name, pop).bytes());
+    }
+    out.push(0x1a);
+    out
+}
+
+
+// Clockwise exterior rings and a counter-clockwise hole.
+const WEST: &[[f64; 2]] = &[[0.0, 0.0], [0.0, 2.0], [2.0, 2.0], [2.0, 0.0], [0.0, 0.0]];
+const EAST: &[[f64; 2]] = &[[10.0, 0.0], [10.0, 4.0], [14.0, 4.0], [14.0, 0.0], [10.0, 0.0]];
+const EAST_HOLE: &[[f64; 2]] = &[[11.0, 1.0], [13.0, 1.0], [13.0, 3.0], [11.0, 3.0], [11.0, 1.0]];
+
+const HEADER: [f64; 8] = [0.0, 0.0, 14.0, 4.0, 0.0, 0.0, 0.0, 0.0];
+
+
+#[test]
+fn groups_rings_into_polygons() {
+    let (shp, shx) = shapefile(5, HEADER, &[polygon(&[WEST, EAST, EAST_HOLE]), polygon(&[WEST])]);
+    let fc = parse_shapefile(&shp, Some(&shx), None).unwrap();
+
+    let ring = |r: &[[f64; 2]]| r.iter().map(|p| p.to_vec()).collect::<Vec<_>>();
+    let values: Vec<&Value> = fc.features.iter().map(|f| &f.geometry.as_ref().unwrap().value).collect();
+    assert_eq!(*values[0], Value::MultiPolygon(vec![vec![ring(WEST)], vec![ring(EAST), ring(EAST_HOLE)]]));
+    assert_eq!(*values[1], Value::Polygon(vec![ring(WEST)]));
+    assert_eq!(fc.to_bbox().unwrap(), Bbox::new(0.0, 0.0, 14.0, 4.0));
+}
+
+
+#[test]
+fn holes_do_not_count() {
+    // A hole that sticks out of its exterior, as in invalid data, is still
+    // a hole and doesn't widen the bbox, as for Value::Polygon.
+    let hole: &[[f64; 2]] = &[[1.0, 1.0], [5.0, 1.0], [5.0, 1.5], [1.0, 1.5], [1.0, 1.0]];
+    let (shp, shx) = shapefile(5, [0.0, 0.0, 5.0, 2.0, 0.0, 0.0, 0.0, 0.0], &[polygon(&[WEST, hole])]);
+    let fc = parse_shapefile(&shp, Some(&shx), None).unwrap();
+    assert_eq!(fc.to_bbox().unwrap(), Bbox::new(0.0, 0.0, 2.0, 2.0));
+}
+
+
+#[test]
+fn counter_clockwise_only_rings_are_exteriors() {
+    let (shp, shx) = shapefile(5, [11.0, 1.0, 13.0, 3.0, 0.0, 0.0, 0.0, 0.0], &[polygon(&[EAST_HOLE])]);
+    let fc = parse_shapefile(&shp, Some(&shx), None).unwrap();
+    assert_eq!(fc.to_bbox().unwrap(), Bbox::new(11.0, 1.0, 13.0, 3.0));
+}
+
+
+#[test]
+fn disjoint_counter_clockwise_rings_all_count() {
+    let ring = |r: &[[f64; 2]]| r.iter().map(|p| p.to_vec()).collect::<Vec<_>>();
+    let west_reversed: Vec<[f64; 2]> = WEST.iter().rev().cloned().collect();
+
+    // With no clockwise ring at all, every ring is an exterior.
+    let (shp, shx) = shapefile(5, [0.0, 0.0, 13.0, 3.0, 0.0, 0.0, 0.0, 0.0],
+                               &[polygon(&[&west_reversed, EAST_HOLE])]);
+    let fc = parse_shapefile(&shp, Some(&shx), None).unwrap();
+    assert_eq!(fc.features[0].geometry.as_ref().unwrap().value,
+               Value::MultiPolygon(vec![vec![ring(&west_reversed)], vec![ring(EAST_HOLE)]]));
+    assert_eq!(fc.to_bbox().unwrap(), Bbox::new(0.0, 0.0, 13.0, 3.0));
+
+    // A single exterior only takes the holes inside it.
+    let (shp, shx) = shapefile(5, [0.0, 0.0, 13.0, 3.0, 0.0, 0.0, 0.0, 0.0], &[polygon(&[WEST, EAST_HOLE])]);
+    let fc = parse_shapefile(&shp, Some(&shx), None).unwrap();
+    assert_eq!(fc.features[0].geometry.as_ref().unwrap().value,
+               Value::MultiPolygon(vec![vec![ring(WEST)], vec![ring(EAST_HOLE)]]));
+    assert_eq!(fc.to_bbox().unwrap(), Bbox::new(0.0, 0.0, 13.0, 3.0));
+}
+
+
+#[test]
+fn index_is_optional() {
+    let (shp, shx) = shapefile(5, HEADER, &[polygon(&[WEST]), null_shape(), polygon(&[EAST])]);
+    assert_eq!(parse_shapefile(&shp, Some(&shx), None).unwrap(), parse_shapefile(&shp, None, None).unwrap());
+}
+
+
+#[test]
+fn null_shapes_are_skipped() {
+    let (shp, _) = shapefile(5, HEADER, &[polygon(&[WEST]), null_shape()]);
+    let geojson = GeoJson::FeatureCollection(parse_shapefile(&shp, None, None).unwrap());
+    assert_eq!(Skipped::count(&geojson).null_geometry, 1);
+}
+
+
+#[test]
+fn header_and_record_extents_are_bbox_members() {
+    let (shp, shx) = shapefile(5, HEADER, &[polygon(&[WEST]), polygon(&[EAST])]);
+    let fc = parse_shapefile(&shp, Some(&shx), None).unwrap();
+    assert_eq!(fc.bbox, Some(vec![0.0, 0.0, 14.0, 4.0]));
+    assert_eq!(fc.features[1].bbox, Some(vec![10.0, 0.0, 14.0, 4.0]));
+    let report = check_bbox_members(&GeoJson::FeatureCollection(fc)).unwrap();
+    assert_eq!(report.checked, 3);
+    assert!(report.mismatches.is_empty());
+
+    // A stale header is reported.
+    let (shp, _) = shapefile(5, [0.0, 0.0, 2.0, 2.0, 0.0, 0.0, 0.0, 0.0], &[polygon(&[WEST]), polygon(&[EAST])]);
+    let geojson = GeoJson::FeatureCollection(parse_shapefile(&shp, None, None).unwrap());
+    let report = check_bbox_members(&geojson).unwrap();
+    assert_eq!(report.mismatches.len(), 1);
+    assert_eq!(report.mismatches[0].path.to_string(), "bbox");
+    assert_eq!(report.mismatches[0].difference, 12.0);
+}
+
+
+#[test]
+fn empty_shapefile_agrees_with_its_header() {
+    for &header in &[[0.0; 8], [f64::NAN; 8]] {
+        for &shape_type in &[5, 15] {
+            let (shp, shx) = shapefile(shape_type, header, &[]);
+            let geojson = GeoJson::FeatureCollection(parse_shapefile(&shp, Some(&shx), None).unwrap());
+            assert!(geojson.to_bbox().unwrap().is_empty());
+            let report = check_bbox_members(&geojson).unwrap();
+            assert_eq!(report.checked, 1);
+            assert!(report.mismatches.is_empty(), "{:?}", report);
+        }
+    }
+
+    // A header declaring an extent for an empty file is still reported.
+    let (shp, _) = shapefile(5, HEADER, &[]);
+    let geojson = GeoJson::FeatureCollection(parse_shapefile(&shp, None, None).unwrap());
+    assert_eq!(check_bbox_members(&geojson).unwrap().mismatches[0].difference, f64::INFINITY);
+}
+
+
+#[test]
+fn z_values() {
+    let header = [1.0, 2.0, 4.0, 5.0, -3.0, 6.0, 0.0, 0.0];
+    let (shp, shx) = shapefile(11, header, &[point_z(1.0, 2.0, 6.0), point_z(4.0, 5.0, -3.0)]);
+    let fc = parse_shapefile(&shp, Some(&shx), None).unwrap();
+    assert_eq!(fc.bbox, Some(vec![1.0, 2.0, -3.0, 4.0, 5.0, 6.0]));
+    assert_eq!(fc.to_bbox().unwrap(), Bbox::new_3d(1.0, 2.0, -3.0, 4.0, 5.0, 6.0));
+}
+
+
+#[test]
+fn dbf_attributes_become_properties() {
+    let (shp, shx) = shapefile(5, HEADER, &[polygon(&[WEST]), polygon(&[EAST])]);
+    let fc = parse_shapefile(&shp, Some(&shx), Some(&dbf(&[("West", "120"), ("East", "")]))).unwrap();
+    let properties = fc.features[0].properties.as_ref().unwrap();
+    assert_eq!(properties["NAME"], "West");
+    assert_eq!(properties["POP"], 120);
+    assert!(fc.features[1].properties.as_ref().unwrap()["POP"].is_null());
+
+    let e = parse_shapefile(&shp, Some(&shx), Some(&dbf(&[("West", "120")]))).unwrap_err();
+    assert!(e.to_string().contains("the .dbf has 1 record(s) but the .shp has 2"), "{}", e);
+}
+
+
+#[test]
+fn truncated_record_is_an_error() {
+    let (mut shp, shx) = shapefile(5, HEADER, &[polygon(&[WEST]), polygon(&[EAST])]);
+    shp.truncate(shp.len() - 10);
+    match parse_shapefile(&shp, Some(&shx), None) {
+        Err(ReadError::Record(2, _)) => (),
+        other => panic!("expected an error in record 2, got {:?}", other),
+    }
+    assert!(parse_shapefile(b"{\"type\": \"Point\"}", None, None).is_err());
+}
@@ -0,0 +1,28 @@
+extern crate par_bbox;
+
+use par_bbox::PhaseStats;
+
+
+#[test]
+fn odd_and_even_counts() {
+    let stats = PhaseStats::new(&[3.0, 1.0, 2.0]).unwrap();
+    assert_eq!(stats, PhaseStats { min: 1.0, median: 2.0, p95: 3.0, max: 3.0 });
+
+    let stats = PhaseStats::new(&[4.0, 1.0, 3.0, 2.0]).unwrap();
+    assert_eq!(stats, PhaseStats { min: 1.0, median: 2.5, p95: 4.0, max: 4.0 });
+
+    let stats = PhaseStats::new(&[0.5]).unwrap();
+    assert_eq!(stats, PhaseStats { min: 0.5, median: 0.5, p95: 0.5, max: 0.5 });
+    assert_eq!(PhaseStats::new(&[]), None);
+}
+
+
+#[test]
+fn p95_is_the_nearest_rank() {
+    // The 95th of 100 samples, and the 19th of 20.
+    let samples: Vec<f64> = (1..101).rev().map(f64::from).collect();
+    assert_eq!(PhaseStats::new(&samples).unwrap().p95, 95.0);
+    let samples: Vec<f64> = (1..21).map(f64::from).collect();
+    let stats = PhaseStats::new(&samples).unwrap();
+    assert_eq!((stats.median, stats.p95, stats.max), (10.5, 19.0, 20.0));
+}
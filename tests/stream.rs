@@ -0,0 +1,110 @@
+extern crate geojson;
+extern crate par_bbox;
+
+mod common;
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+
+use par_bbox::{ReadError, Skipped, ToBbox, stream_bbox};
+use common::load_polys;
+
+
+#[test]
+fn stream_matches_in_memory() {
+    let geojson = load_polys();
+
+    for &batch_size in &[1, 2, 3, 1000] {
+        let file = File::open("data/polys.geojson").unwrap();
+        let streamed = stream_bbox(BufReader::new(file), batch_size).unwrap();
+        assert_eq!(streamed.bbox, geojson.to_bbox().unwrap());
+        assert_eq!(streamed.skipped, Skipped::default());
+    }
+}
+
+
+#[test]
+fn stream_counts_skipped_features() {
+    let data = r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "properties": {}, "geometry": null},
+        {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [1, 2]}},
+        {"type": "Feature", "properties": {}, "geometry": {"type": "MultiPolygon", "coordinates": []}}
+    ]}"#;
+    let streamed = stream_bbox(data.as_bytes(), 2).unwrap();
+    assert_eq!(streamed.bbox, par_bbox::Bbox::new(1.0, 2.0, 1.0, 2.0));
+    assert_eq!(streamed.skipped, Skipped { null_geometry: 1, empty_geometry: 1 });
+}
+
+
+#[test]
+fn stream_error_paths_count_across_batches() {
+    let data = r#"{"features": [
+        {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [1, 2]}},
+        {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [1, 2]}},
+        {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [1, 2]}},
+        {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [1]}}
+    ], "type": "FeatureCollection"}"#;
+    match stream_bbox(data.as_bytes(), 2) {
+        Err(ReadError::Bbox(e)) => {
+            assert_eq!(e.path().to_string(), "features[3].geometry.coordinates")
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+
+#[test]
+fn stream_handles_documents_without_features() {
+    let data = r#"{"type": "Feature", "properties": {"name": "x"},
+                   "geometry": {"type": "LineString", "coordinates": [[0, 0], [3, 4]]}}"#;
+    let streamed = stream_bbox(data.as_bytes(), 10).unwrap();
+    assert_eq!(streamed.bbox, par_bbox::Bbox::new(0.0, 0.0, 3.0, 4.0));
+
+    match stream_bbox(&b"{\"type\": \"FeatureCollection\", \"features\": [}"[..], 10) {
+        Err(ReadError::Json(_)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+
+// Reads a byte at a time, so that UTF-8 sequences are split across reads.
+struct ByteReader<'a>(&'a [u8]);
+
+
+impl<'a> Read for ByteReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.0.len().min(buf.len()).min(1);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+
+#[test]
+fn stream_explains_non_utf8_and_truncated_input() {
+    let data = "{\"type\": \"Feature\", \"properties\": {\"name\": \"caf\u{e9}\"}, \"geometry\": null}";
+    assert!(stream_bbox(ByteReader(data.as_bytes()), 2).unwrap().bbox.is_empty());
+
+    let data = b"{\"type\": \"Feature\", \"properties\": {\"name\": \"caf\xe9\"}, \"geometry\": null}";
+    for result in [stream_bbox(&data[..], 2), stream_bbox(ByteReader(data), 2)] {
+        match result {
+            Err(ReadError::NotUtf8(47, _)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+    // A sequence cut off by the end of the input.
+    match stream_bbox(ByteReader(b"{\"type\": \"Feature\", \"properties\": {\"name\": \"caf\xc3"), 2) {
+        Err(ReadError::NotUtf8(47, _)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    match stream_bbox(&b"{\"type\": \"Point\", \"coordinates\": [1,"[..], 2) {
+        Err(ReadError::Truncated(_)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    match stream_bbox(&b" \n"[..], 2) {
+        Err(ReadError::Empty) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
@@ -0,0 +1,171 @@
+extern crate geojson;
+extern crate par_bbox;
+
+use geojson::{Geometry, Value};
+use par_bbox::{Bbox, ReadError, ToBbox, parse_hex_wkb, parse_wkb, parse_wkt, wkt_bbox};
+
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+
+// A little-endian WKB geometry header followed by the given values.
+fn wkb(code: u32, counts_and_coords: &[Result<u32, f64>]) -> Vec<u8> {
+    let mut out = vec![1];
+    out.extend_from_slice(&code.to_le_bytes());
+    for v in counts_and_coords {
+        match *v {
+            Ok(n) => out.extend_from_slice(&n.to_le_bytes()),
+            Err(x) => out.extend_from_slice(&x.to_le_bytes()),
+        }
+    }
+    out
+}
+
+
+#[test]
+fn parses_all_geometry_types() {
+    let cases = vec![
+        ("POINT (1 2)", Value::Point(vec![1.0, 2.0])),
+        ("LINESTRING (1 2, 3 4)", Value::LineString(vec![vec![1.0, 2.0], vec![3.0, 4.0]])),
+        ("POLYGON ((0 0, 4 0, 4 4, 0 0), (1 1, 2 1, 2 2, 1 1))",
+         Value::Polygon(vec![vec![vec![0.0, 0.0], vec![4.0, 0.0], vec![4.0, 4.0], vec![0.0, 0.0]],
+                             vec![vec![1.0, 1.0], vec![2.0, 1.0], vec![2.0, 2.0], vec![1.0, 1.0]]])),
+        ("MULTIPOINT ((1 2), (3 4))", Value::MultiPoint(vec![vec![1.0, 2.0], vec![3.0, 4.0]])),
+        ("MULTIPOINT (1 2, 3 4)", Value::MultiPoint(vec![vec![1.0, 2.0], vec![3.0, 4.0]])),
+        ("MULTILINESTRING ((1 2, 3 4), (5 6, 7 8))",
+         Value::MultiLineString(vec![vec![vec![1.0, 2.0], vec![3.0, 4.0]], vec![vec![5.0, 6.0], vec![7.0, 8.0]]])),
+        ("MULTIPOLYGON (((0 0, 1 0, 1 1, 0 0)), EMPTY)",
+         Value::MultiPolygon(vec![vec![vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![1.0, 1.0], vec![0.0, 0.0]]], vec![]])),
+        ("GEOMETRYCOLLECTION (POINT (1 2), LINESTRING EMPTY)",
+         Value::GeometryCollection(vec![Geometry::new(Value::Point(vec![1.0, 2.0])),
+                                        Geometry::new(Value::LineString(vec![]))])),
+        ("  point(-1.5e2 +2.)  ", Value::Point(vec![-150.0, 2.0])),
+        ("SRID=4326;POINT (1 2)", Value::Point(vec![1.0, 2.0])),
+    ];
+    for (text, expected) in cases {
+        assert_eq!(parse_wkt(text).unwrap(), expected, "{}", text);
+    }
+}
+
+
+#[test]
+fn empty_geometries_have_empty_bboxes() {
+    for name in &["POINT", "LINESTRING", "POLYGON", "MULTIPOINT", "MULTILINESTRING", "MULTIPOLYGON",
+                  "GEOMETRYCOLLECTION"] {
+        for dimension in &["", " Z", " M", " ZM"] {
+            let text = format!("{}{} EMPTY", name, dimension);
+            let value = parse_wkt(&text).unwrap();
+            assert!(value.to_bbox().unwrap().is_empty(), "{}", text);
+        }
+    }
+    assert_eq!(parse_wkt("POINT EMPTY").unwrap(), Value::MultiPoint(vec![]));
+}
+
+
+#[test]
+fn keeps_z_and_drops_m() {
+    assert_eq!(parse_wkt("POINT Z (1 2 3)").unwrap(), Value::Point(vec![1.0, 2.0, 3.0]));
+    assert_eq!(parse_wkt("POINT (1 2 3)").unwrap(), Value::Point(vec![1.0, 2.0, 3.0]));
+    assert_eq!(parse_wkt("POINT M (1 2 3)").unwrap(), Value::Point(vec![1.0, 2.0]));
+    assert_eq!(parse_wkt("POINTM (1 2 3)").unwrap(), Value::Point(vec![1.0, 2.0]));
+    assert_eq!(parse_wkt("POINT ZM (1 2 3 4)").unwrap(), Value::Point(vec![1.0, 2.0, 3.0]));
+    assert_eq!(parse_wkt("LINESTRING Z (0 0 -1, 1 1 5)").unwrap().to_bbox().unwrap(),
+               Bbox::new_3d(0.0, 0.0, -1.0, 1.0, 1.0, 5.0));
+}
+
+
+#[test]
+fn reports_wkt_errors_with_their_column() {
+    for &(text, column) in &[("POINT (1)", 9), ("POINT (1 2", 11), ("CIRCLE (1 2)", 1), ("POINT (1 2) x", 13)] {
+        match parse_wkt(text) {
+            Err(ReadError::Wkt(ref message)) => {
+                assert!(message.ends_with(&format!("at column {}", column)), "{}: {}", text, message)
+            }
+            other => panic!("{}: expected a WKT error, got {:?}", text, other),
+        }
+    }
+}
+
+
+#[test]
+fn parses_postgis_ewkb() {
+    // SELECT 'SRID=4326;POINT(1 2)'::geometry
+    let value = parse_hex_wkb("0101000020E6100000000000000000F03F0000000000000040").unwrap();
+    assert_eq!(value, Value::Point(vec![1.0, 2.0]));
+
+    // A LineString with the EWKB z flag, in lower-case hex.
+    let data = wkb(0x8000_0002, &[Ok(2), Err(0.0), Err(0.0), Err(-1.0), Err(3.0), Err(4.0), Err(5.0)]);
+    assert_eq!(parse_hex_wkb(&hex(&data).to_lowercase()).unwrap(),
+               Value::LineString(vec![vec![0.0, 0.0, -1.0], vec![3.0, 4.0, 5.0]]));
+}
+
+
+#[test]
+fn parses_iso_wkb_dimensions() {
+    let zm = wkb(3001, &[Err(1.0), Err(2.0), Err(3.0), Err(4.0)]);
+    assert_eq!(parse_wkb(&zm).unwrap(), Value::Point(vec![1.0, 2.0, 3.0]));
+    let m = wkb(2001, &[Err(1.0), Err(2.0), Err(3.0)]);
+    assert_eq!(parse_wkb(&m).unwrap(), Value::Point(vec![1.0, 2.0]));
+
+    // Big-endian.
+    let mut big = vec![0, 0, 0, 0, 1];
+    big.extend_from_slice(&1f64.to_be_bytes());
+    big.extend_from_slice(&2f64.to_be_bytes());
+    assert_eq!(parse_wkb(&big).unwrap(), Value::Point(vec![1.0, 2.0]));
+}
+
+
+#[test]
+fn wkb_matches_wkt() {
+    let point = |x: f64, y: f64| wkb(1, &[Err(x), Err(y)]);
+    let mut multi = wkb(4, &[Ok(3)]);
+    multi.extend(point(1.0, 2.0));
+    multi.extend(point(f64::NAN, f64::NAN));
+    multi.extend(point(3.0, 4.0));
+    assert_eq!(parse_wkb(&multi).unwrap(), parse_wkt("MULTIPOINT (1 2, EMPTY, 3 4)").unwrap());
+
+    let mut collection = wkb(7, &[Ok(2)]);
+    collection.extend(point(f64::NAN, f64::NAN));
+    collection.extend(wkb(3, &[Ok(1), Ok(4), Err(0.0), Err(0.0), Err(1.0), Err(0.0), Err(1.0), Err(1.0),
+                               Err(0.0), Err(0.0)]));
+    assert_eq!(parse_wkb(&collection).unwrap(),
+               parse_wkt("GEOMETRYCOLLECTION (POINT EMPTY, POLYGON ((0 0, 1 0, 1 1, 0 0)))").unwrap());
+
+    for &(code, text) in &[(2, "LINESTRING EMPTY"), (3, "POLYGON EMPTY"), (5, "MULTILINESTRING EMPTY"),
+                           (6, "MULTIPOLYGON EMPTY"), (7, "GEOMETRYCOLLECTION EMPTY")] {
+        assert_eq!(parse_wkb(&wkb(code, &[Ok(0)])).unwrap(), parse_wkt(text).unwrap());
+    }
+}
+
+
+#[test]
+fn rejects_corrupt_wkb() {
+    assert!(parse_hex_wkb("0101").is_err());
+    assert!(parse_hex_wkb("010").is_err());
+    // A count far larger than the data.
+    assert!(parse_wkb(&wkb(2, &[Ok(u32::MAX)])).is_err());
+    assert!(parse_wkb(&wkb(8, &[])).is_err());
+}
+
+
+#[test]
+fn mixed_lines_are_merged() {
+    let ewkb = "0101000020E6100000000000000000F03F0000000000000040";
+    let lines = format!("POINT (5 -3)\n\n{}\r\nPOLYGON EMPTY\nLINESTRING (0 0, 10 1)\n", ewkb);
+    for &chunk_size in &[1, 2, 100] {
+        let streamed = wkt_bbox(lines.as_bytes(), chunk_size).unwrap();
+        assert_eq!(streamed.bbox, Bbox::new(0.0, -3.0, 10.0, 2.0));
+    }
+}
+
+
+#[test]
+fn errors_name_the_line() {
+    let lines = "POINT (1 2)\n\nPOINT (1 2)\nPOINT (x)\n";
+    match wkt_bbox(lines.as_bytes(), 2) {
+        Err(ReadError::Record(4, ref e)) => assert!(e.to_string().starts_with("invalid WKT"), "{}", e),
+        other => panic!("expected an error on line 4, got {:?}", other),
+    }
+}